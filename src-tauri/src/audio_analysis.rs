@@ -0,0 +1,448 @@
+//! Pure signal/text analysis helpers, split out of `lib.rs` so the VAD and
+//! noise-filter heuristics can be unit tested and tuned without touching
+//! the capture pipeline. Nothing in here does I/O or holds locks.
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+/// Post-transcription noise filtering. All the rules live in one place so the
+/// inline checks and the blocklist can't drift apart:
+/// - `enabled`: when false, every transcription passes through untouched
+/// - `blocklist`: substrings that mark a result as noise (bracketed Whisper
+///   annotations like `[BLANK_AUDIO]`, music glyphs, filler sounds)
+/// - `min_length`: trimmed results shorter than this are dropped as noise
+/// - `max_repetition_ratio`: a multi-word result is dropped when one word
+///   makes up more than this share of it (catches "you you you you")
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptionFilter {
+    pub enabled: bool,
+    pub blocklist: Vec<String>,
+    pub min_length: usize,
+    pub max_repetition_ratio: f64,
+}
+
+impl Default for TranscriptionFilter {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            blocklist: [
+                "[BLANK_AUDIO]", "[", "]", "(", ")", "♪", "♫", "♬", "♭", "♯",
+                "mmm", "uhh", "umm", "err", "ahh",
+                "...", "///", "---",
+            ].iter().map(|s| s.to_string()).collect(),
+            min_length: 2,
+            max_repetition_ratio: 0.8,
+        }
+    }
+}
+
+impl TranscriptionFilter {
+    pub fn is_noise(&self, text: &str) -> bool {
+        if !self.enabled {
+            return false;
+        }
+
+        let trimmed = text.trim();
+
+        // Check for very short transcriptions (likely noise)
+        if trimmed.len() < self.min_length {
+            info!("Filtering out very short transcription: '{}'", text);
+            return true;
+        }
+
+        // Check if text contains blocklisted noise indicators
+        let text_lower = trimmed.to_lowercase();
+        for pattern in &self.blocklist {
+            if !pattern.is_empty() && text_lower.contains(&pattern.to_lowercase()) {
+                info!("Filtering out noise pattern: '{}' in '{}'", pattern, text);
+                return true;
+            }
+        }
+
+        // Check for repetitive patterns (like "a a a a")
+        let words: Vec<&str> = trimmed.split_whitespace().collect();
+        if words.len() > 3 {
+            let first_word = words[0];
+            let repetitions = words.iter().filter(|&&word| word == first_word).count();
+            if repetitions as f64 > words.len() as f64 * self.max_repetition_ratio {
+                info!("Filtering out repetitive pattern: '{}'", text);
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+/// Rule-based readability pass for caption display: base models often emit
+/// all-lowercase, unpunctuated text. Capitalizes sentence starts, restores
+/// canonical casing for a small set of known tokens (the same vocabulary
+/// the decoding prompt biases toward), and closes final segments with a
+/// period. Deliberately no ML - wrong guesses here are worse than
+/// lowercase, so the rules stay conservative.
+pub struct TextFormatter {
+    /// Canonical spellings applied case-insensitively to whole tokens.
+    pub proper_nouns: Vec<String>,
+}
+
+impl Default for TextFormatter {
+    fn default() -> Self {
+        Self {
+            proper_nouns: [
+                "I", "React", "TypeScript", "JavaScript", "Node.js", "Next.js",
+                "CSS", "HTML", "API", "HTTP", "JSON", "SQL", "WebSocket",
+                "GitHub", "Rust", "Python", "useEffect", "useState",
+            ].iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+impl TextFormatter {
+    /// Format one caption. `is_final` segments get terminal punctuation;
+    /// partials are left open since the sentence is still going.
+    pub fn format(&self, text: &str, is_final: bool) -> String {
+        let mut words: Vec<String> = Vec::new();
+        let mut sentence_start = true;
+
+        for raw in text.split_whitespace() {
+            // Canonical casing for known vocabulary, ignoring any trailing
+            // punctuation stuck to the token
+            let bare = raw.trim_end_matches(|c: char| !c.is_alphanumeric());
+            let mut word = match self.proper_nouns.iter().find(|n| n.eq_ignore_ascii_case(bare)) {
+                Some(canonical) => format!("{}{}", canonical, &raw[bare.len()..]),
+                None => raw.to_string(),
+            };
+
+            if sentence_start {
+                word = capitalize_first(&word);
+            }
+            sentence_start = word.ends_with(['.', '!', '?']);
+            words.push(word);
+        }
+
+        let mut result = words.join(" ");
+        if is_final && result.chars().last().is_some_and(|c| c.is_alphanumeric()) {
+            result.push('.');
+        }
+        result
+    }
+}
+
+fn capitalize_first(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Returns (display rms, display peak, raw rms, raw peak). The display pair
+/// is amplified and clamped for the meter; the raw pair is untouched.
+pub(crate) fn calculate_audio_levels(audio_data: &[f32], amplification: f64) -> (f64, f64, f64, f64) {
+    if audio_data.is_empty() {
+        return (0.0, 0.0, 0.0, 0.0);
+    }
+
+    // Calculate RMS (Root Mean Square) for average audio level
+    let mean_square: f64 = audio_data.iter()
+        .map(|&sample| (sample as f64).powi(2))
+        .sum::<f64>() / audio_data.len() as f64;
+
+    let rms_value = mean_square.sqrt();
+
+    // Peak shows transients that RMS smooths away (like a DAW input meter)
+    let peak_value = audio_data.iter()
+        .map(|&sample| (sample as f64).abs())
+        .fold(0.0, f64::max);
+
+    // Apply amplification factor to make levels more visible
+    // Audio samples are typically normalized between -1.0 and 1.0
+    // But actual speech/music levels are often much lower
+    // Clamp each independently to [0, 1]; the raw values stay unclamped
+    let rms = (rms_value * amplification).min(1.0);
+    let peak = (peak_value * amplification).min(1.0);
+
+    (rms, peak, rms_value, peak_value)
+}
+
+pub(crate) fn detect_voice_activity(audio_data: &[f32], threshold: f64) -> bool {
+    if audio_data.is_empty() {
+        return false;
+    }
+
+    // Calculate energy with frequency weighting
+    let weighted_energy: f64 = audio_data.iter()
+        .enumerate()
+        .map(|(i, &sample)| {
+            let freq_weight = (i as f64 / audio_data.len() as f64).min(1.0);
+            (sample as f64 * freq_weight).powi(2)
+        })
+        .sum::<f64>() / audio_data.len() as f64;
+
+    // Calculate zero crossing rate with improved accuracy
+    let zero_crossings = audio_data.windows(2)
+        .filter(|window| {
+            let sign_change = (window[0] > 0.0) != (window[1] > 0.0);
+            let magnitude = (window[0] - window[1]).abs();
+            sign_change && magnitude > 0.01 // Filter out tiny fluctuations
+        })
+        .count();
+
+    let zcr = zero_crossings as f64 / audio_data.len() as f64;
+
+    // Calculate spectral centroid (rough approximation)
+    let spectral_sum: f64 = audio_data.iter()
+        .enumerate()
+        .map(|(i, &sample)| i as f64 * (sample as f64).abs())
+        .sum::<f64>();
+
+    let magnitude_sum: f64 = audio_data.iter()
+        .map(|&sample| (sample as f64).abs())
+        .sum::<f64>();
+
+    let spectral_centroid = if magnitude_sum > 0.0 {
+        spectral_sum / magnitude_sum / audio_data.len() as f64
+    } else {
+        0.0
+    };
+
+    // Improved voice activity detection using multiple features
+    weighted_energy > threshold && // Energy check
+    zcr > 0.01 && zcr < 0.35 && // More permissive ZCR range
+    spectral_centroid > 0.1 && spectral_centroid < 0.3 // Typical range for speech
+}
+
+/// Accumulates callback buffers into fixed-length analysis frames so the
+/// spectral features in `detect_voice_activity` are always computed over
+/// the same window, whatever buffer size the hardware hands us. A 1024-
+/// sample cpal buffer at 48kHz is ~21ms, but at 16kHz it is 64ms and some
+/// backends deliver far less - per-buffer spectral estimates drift with
+/// that. Frames are consumed back to back with no overlap.
+pub(crate) struct VadFrameAccumulator {
+    pending: Vec<f32>,
+    frame_size: usize,
+    last_decision: bool,
+}
+
+impl VadFrameAccumulator {
+    pub(crate) fn new(frame_size: usize) -> Self {
+        Self {
+            pending: Vec::with_capacity(frame_size.max(1) * 2),
+            frame_size: frame_size.max(1),
+            last_decision: false,
+        }
+    }
+
+    /// Frame length for an analysis window in milliseconds at the given
+    /// sample rate (the VadConfig field is expressed in ms).
+    pub(crate) fn for_window(window_ms: u64, sample_rate: u32) -> Self {
+        Self::new((sample_rate as u64 * window_ms / 1000) as usize)
+    }
+
+    /// Feed one callback buffer. Returns the decision for each analysis
+    /// frame completed by this buffer - empty while a frame is still
+    /// filling; the most recent decision stays readable via `is_voice`.
+    pub(crate) fn push(&mut self, samples: &[f32], threshold: f64) -> Vec<bool> {
+        self.pending.extend_from_slice(samples);
+
+        let mut decisions = Vec::new();
+        while self.pending.len() >= self.frame_size {
+            let frame: Vec<f32> = self.pending.drain(..self.frame_size).collect();
+            let decision = detect_voice_activity(&frame, threshold);
+            self.last_decision = decision;
+            decisions.push(decision);
+        }
+        decisions
+    }
+
+    /// The decision for the most recently completed frame; false until the
+    /// first frame fills.
+    pub(crate) fn is_voice(&self) -> bool {
+        self.last_decision
+    }
+
+    /// Drop any partially filled frame, e.g. when capture restarts.
+    pub(crate) fn reset(&mut self) {
+        self.pending.clear();
+        self.last_decision = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f32, sample_rate: f32, seconds: f32, amplitude: f32) -> Vec<f32> {
+        let count = (sample_rate * seconds) as usize;
+        (0..count)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate).sin())
+            .collect()
+    }
+
+    /// A crude speech stand-in: a voiced burst at the start of the buffer
+    /// followed by silence, which puts the temporal centroid in the range
+    /// the VAD treats as speech.
+    fn speech_like_buffer() -> Vec<f32> {
+        let mut samples = sine(440.0, 16000.0, 0.25, 0.5);
+        samples.resize(16000, 0.0);
+        samples
+    }
+
+    #[test]
+    fn levels_of_empty_input_are_zero() {
+        assert_eq!(calculate_audio_levels(&[], 10.0), (0.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn levels_of_silence_are_zero() {
+        let silence = vec![0.0f32; 16000];
+        assert_eq!(calculate_audio_levels(&silence, 10.0), (0.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn levels_of_pure_tone_match_sine_math() {
+        let tone = sine(440.0, 16000.0, 1.0, 0.5);
+        let (_, _, raw_rms, raw_peak) = calculate_audio_levels(&tone, 1.0);
+
+        // RMS of a sine is amplitude / sqrt(2)
+        assert!((raw_rms - 0.5 / 2.0f64.sqrt()).abs() < 0.01);
+        assert!((raw_peak - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn display_levels_stay_in_unit_range_for_any_input() {
+        // Deterministic pseudo-random buffers; whatever goes in, the
+        // amplified display pair must stay inside [0, 1]
+        let mut state: u32 = 0x2545_f491;
+        for _ in 0..20 {
+            let samples: Vec<f32> = (0..512)
+                .map(|_| {
+                    state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                    (state as f64 / u32::MAX as f64 * 4.0 - 2.0) as f32 // beyond full scale
+                })
+                .collect();
+
+            for amplification in [0.1, 1.0, 10.0, 1000.0] {
+                let (rms, peak, ..) = calculate_audio_levels(&samples, amplification);
+                assert!((0.0..=1.0).contains(&rms), "rms {} out of range", rms);
+                assert!((0.0..=1.0).contains(&peak), "peak {} out of range", peak);
+            }
+        }
+    }
+
+    #[test]
+    fn vad_rejects_empty_input() {
+        assert!(!detect_voice_activity(&[], 0.0));
+    }
+
+    #[test]
+    fn vad_rejects_silence() {
+        let silence = vec![0.0f32; 16000];
+        assert!(!detect_voice_activity(&silence, 0.0001));
+    }
+
+    #[test]
+    fn vad_rejects_sustained_pure_tone() {
+        // A tone filling the whole buffer has its temporal centroid near
+        // the middle (~0.5), well outside the speech range
+        let tone = sine(440.0, 16000.0, 1.0, 0.5);
+        assert!(!detect_voice_activity(&tone, 0.0001));
+    }
+
+    #[test]
+    fn vad_accepts_speech_like_burst() {
+        assert!(detect_voice_activity(&speech_like_buffer(), 0.0001));
+    }
+
+    #[test]
+    fn filter_drops_blocklisted_and_repetitive_text() {
+        let filter = TranscriptionFilter::default();
+        assert!(filter.is_noise("[BLANK_AUDIO]"));
+        assert!(filter.is_noise("you you you you you"));
+        assert!(filter.is_noise("a"));
+        assert!(!filter.is_noise("tell me about your last project"));
+    }
+
+    #[test]
+    fn disabled_filter_passes_everything() {
+        let filter = TranscriptionFilter { enabled: false, ..Default::default() };
+        assert!(!filter.is_noise("[BLANK_AUDIO]"));
+    }
+
+    /// One 25ms analysis frame the VAD accepts: a voiced burst over the
+    /// first 40% of the frame, then silence, which lands the temporal
+    /// centroid and ZCR inside the speech ranges.
+    fn burst_frame() -> Vec<f32> {
+        let mut frame = sine(800.0, 16000.0, 0.01, 0.8);
+        frame.resize(400, 0.0);
+        frame
+    }
+
+    #[test]
+    fn formatter_capitalizes_and_punctuates_a_final_caption() {
+        let formatter = TextFormatter::default();
+        assert_eq!(
+            formatter.format("so i used react on the frontend", true),
+            "So I used React on the frontend."
+        );
+    }
+
+    #[test]
+    fn formatter_capitalizes_after_sentence_punctuation() {
+        let formatter = TextFormatter::default();
+        assert_eq!(
+            formatter.format("it works. pretty well! what about css?", false),
+            "It works. Pretty well! What about CSS?"
+        );
+    }
+
+    #[test]
+    fn formatter_leaves_partials_unterminated_and_good_text_alone() {
+        let formatter = TextFormatter::default();
+        assert_eq!(formatter.format("and then the", false), "And then the");
+        assert_eq!(formatter.format("The API is done.", true), "The API is done.");
+    }
+
+    #[test]
+    fn accumulator_decisions_are_independent_of_buffer_size() {
+        // Half a second of speech-like frames followed by half a second of
+        // silence, fed in wildly different chunk sizes; the per-frame
+        // decisions must come out identical every time
+        let mut signal: Vec<f32> = (0..20).flat_map(|_| burst_frame()).collect();
+        signal.resize(16000, 0.0);
+
+        let frame_size = 400; // 25ms at 16kHz
+
+        let expected: Vec<bool> = signal
+            .chunks_exact(frame_size)
+            .map(|frame| detect_voice_activity(frame, 0.0001))
+            .collect();
+        assert!(expected.contains(&true) && expected.contains(&false));
+
+        for chunk_size in [64, 400, 1024, 4096] {
+            let mut accumulator = VadFrameAccumulator::new(frame_size);
+            let decisions: Vec<bool> = signal
+                .chunks(chunk_size)
+                .flat_map(|chunk| accumulator.push(chunk, 0.0001))
+                .collect();
+            assert_eq!(decisions, expected, "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn accumulator_holds_last_decision_while_a_frame_fills() {
+        let mut accumulator = VadFrameAccumulator::for_window(25, 16000);
+        assert!(!accumulator.is_voice());
+
+        // A full voiced frame flips the decision on, and a sub-frame
+        // sliver of silence afterwards does not flip it back
+        assert_eq!(accumulator.push(&burst_frame(), 0.0001), vec![true]);
+        assert!(accumulator.is_voice());
+
+        assert!(accumulator.push(&[0.0f32; 100], 0.0001).is_empty());
+        assert!(accumulator.is_voice());
+
+        accumulator.reset();
+        assert!(!accumulator.is_voice());
+    }
+}
@@ -1,9 +1,102 @@
+use std::time::{Duration, Instant};
+
 use serde::{Deserialize, Serialize};
-use log::{info, error};
+use log::{info, error, warn};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GeminiRequest {
     contents: Vec<Content>,
+    #[serde(rename = "safetySettings")]
+    safety_settings: Vec<SafetySetting>,
+    #[serde(rename = "generationConfig", skip_serializing_if = "Option::is_none")]
+    generation_config: Option<GenerationConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GenerationConfig {
+    #[serde(rename = "maxOutputTokens")]
+    max_output_tokens: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SafetySetting {
+    category: String,
+    threshold: String,
+}
+
+/// The harm categories Gemini lets callers tune. We apply one threshold
+/// uniformly; interview answers shouldn't trip any of them.
+const SAFETY_CATEGORIES: [&str; 4] = [
+    "HARM_CATEGORY_HARASSMENT",
+    "HARM_CATEGORY_HATE_SPEECH",
+    "HARM_CATEGORY_SEXUALLY_EXPLICIT",
+    "HARM_CATEGORY_DANGEROUS_CONTENT",
+];
+
+pub const DEFAULT_SAFETY_THRESHOLD: &str = "BLOCK_ONLY_HIGH";
+
+pub const VALID_SAFETY_THRESHOLDS: [&str; 4] = [
+    "BLOCK_NONE",
+    "BLOCK_ONLY_HIGH",
+    "BLOCK_MEDIUM_AND_ABOVE",
+    "BLOCK_LOW_AND_ABOVE",
+];
+
+/// Total per-request deadline; long enough for Gemini to compose an answer,
+/// short enough that a hung connection doesn't stall the interview flow.
+pub const DEFAULT_TIMEOUT_SECS: u64 = 20;
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 5;
+
+/// How many times a request is retried after a transient drop (timeout or
+/// network-level failure with no HTTP status). Distinct from any retry
+/// policy on the initial request validation - API errors with a status
+/// code are never retried.
+pub const DEFAULT_TRANSIENT_RETRIES: u32 = 2;
+
+/// How long interview answers should run. Levels map onto both a
+/// generation token cap and a length instruction injected into the prompt,
+/// which is more user-meaningful than raw token counts. Summaries are not
+/// affected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnswerBrevity {
+    /// A few plain sentences, suitable for reading mid-conversation.
+    Terse,
+    #[default]
+    Normal,
+    /// Room for concrete examples and longer explanations.
+    Detailed,
+}
+
+impl AnswerBrevity {
+    pub fn parse(level: &str) -> Option<Self> {
+        match level {
+            "terse" => Some(AnswerBrevity::Terse),
+            "normal" => Some(AnswerBrevity::Normal),
+            "detailed" => Some(AnswerBrevity::Detailed),
+            _ => None,
+        }
+    }
+
+    fn max_output_tokens(self) -> u32 {
+        match self {
+            AnswerBrevity::Terse => 256,
+            AnswerBrevity::Normal => 1024,
+            AnswerBrevity::Detailed => 2048,
+        }
+    }
+
+    fn instruction(self) -> Option<&'static str> {
+        match self {
+            AnswerBrevity::Terse => {
+                Some("Keep the answer to two or three plain sentences, with no bullet points or headings.")
+            }
+            AnswerBrevity::Normal => None,
+            AnswerBrevity::Detailed => {
+                Some("Give a thorough answer with concrete examples where they help.")
+            }
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -16,79 +109,433 @@ pub struct Part {
     text: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(untagged)]
-pub enum GeminiResponse {
-    Success {
-        candidates: Vec<Candidate>,
-    },
-    Error {
-        error: GeminiError,
-    },
+/// Token accounting from the `usageMetadata` block of a Gemini response.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct GeminiUsage {
+    #[serde(rename = "promptTokenCount", default)]
+    pub prompt_tokens: u32,
+    #[serde(rename = "candidatesTokenCount", default)]
+    pub candidates_tokens: u32,
+    #[serde(rename = "totalTokenCount", default)]
+    pub total_tokens: u32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct GeminiError {
-    code: i32,
-    message: String,
-    status: String,
+/// Post-processing applied to Gemini answers before they reach the UI.
+/// Answers are meant to be spoken aloud, so markdown scaffolding and chatty
+/// framing ("Sure, ...", trailing "Let me know if...") just get in the way.
+/// All the rules are data so the frontend can tune them:
+/// - `strip_markdown`: drop headers, bullets, emphasis markers, and backticks
+/// - `remove_phrases`: substrings deleted wherever they appear
+/// - `leading_openers`: phrases stripped from the start of the answer
+/// - `trailing_offers`: a final line starting with one of these is dropped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseCleaner {
+    pub strip_markdown: bool,
+    pub remove_phrases: Vec<String>,
+    pub leading_openers: Vec<String>,
+    pub trailing_offers: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Candidate {
-    content: Content,
+impl Default for ResponseCleaner {
+    fn default() -> Self {
+        Self {
+            strip_markdown: true,
+            remove_phrases: [
+                "[Key Points]",
+                "[Response]",
+                "Thank you for your question.",
+                "That's a great question.",
+                "Thank you for asking.",
+            ].iter().map(|s| s.to_string()).collect(),
+            leading_openers: [
+                "Sure,", "Sure!", "Certainly,", "Certainly!",
+                "Of course,", "Of course!", "Absolutely,", "Absolutely!",
+            ].iter().map(|s| s.to_string()).collect(),
+            trailing_offers: [
+                "Let me know if",
+                "Feel free to",
+                "I hope this helps",
+                "Is there anything else",
+            ].iter().map(|s| s.to_string()).collect(),
+        }
+    }
 }
 
-pub struct GeminiService {
-    api_key: String,
-    base_url: String,
-    context: String,
+impl ResponseCleaner {
+    pub fn clean(&self, text: &str) -> String {
+        let mut result = text.to_string();
+
+        for phrase in &self.remove_phrases {
+            if !phrase.is_empty() {
+                result = result.replace(phrase, "");
+            }
+        }
+
+        // Strip openers from the front; models sometimes stack them
+        // ("Sure! Certainly, ..."), so keep going until none match
+        let mut trimmed = result.trim_start();
+        loop {
+            let mut stripped = false;
+            for opener in &self.leading_openers {
+                if opener.is_empty() || trimmed.len() < opener.len() || !trimmed.is_char_boundary(opener.len()) {
+                    continue;
+                }
+                if trimmed[..opener.len()].eq_ignore_ascii_case(opener) {
+                    trimmed = trimmed[opener.len()..].trim_start();
+                    stripped = true;
+                    break;
+                }
+            }
+            if !stripped {
+                break;
+            }
+        }
+        result = trimmed.to_string();
+
+        let mut lines: Vec<String> = result.lines()
+            .map(|line| {
+                let mut line = line.trim_end().to_string();
+                if self.strip_markdown {
+                    let stripped = line.trim_start();
+                    let stripped = if stripped.starts_with('#') {
+                        stripped.trim_start_matches('#').trim_start()
+                    } else if let Some(rest) = stripped.strip_prefix("- ") {
+                        rest
+                    } else if let Some(rest) = stripped.strip_prefix("* ") {
+                        rest
+                    } else if let Some(rest) = stripped.strip_prefix("> ") {
+                        rest
+                    } else {
+                        stripped
+                    };
+                    line = stripped.replace("**", "").replace("__", "").replace('`', "");
+                }
+                line
+            })
+            .collect();
+
+        // Drop trailing follow-up offers ("Let me know if you'd like...")
+        // and any blank lines they leave behind
+        while let Some(last) = lines.last() {
+            let lower = last.trim().to_lowercase();
+            let is_offer = self.trailing_offers.iter()
+                .any(|offer| !offer.is_empty() && lower.starts_with(&offer.to_lowercase()));
+            if lower.is_empty() || is_offer {
+                lines.pop();
+            } else {
+                break;
+            }
+        }
+
+        // Collapse runs of blank lines left by removed scaffolding
+        let mut collapsed: Vec<String> = Vec::new();
+        for line in lines {
+            if line.trim().is_empty() && collapsed.last().map_or(true, |prev: &String| prev.trim().is_empty()) {
+                continue;
+            }
+            collapsed.push(line);
+        }
+
+        collapsed.join("\n").trim().to_string()
+    }
 }
 
-impl GeminiService {
-    pub fn new(api_key: String, context: String) -> Self {
+/// A cleaned interview answer plus the token usage it consumed (when the API
+/// reported it).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterviewResponse {
+    pub text: String,
+    pub usage: Option<GeminiUsage>,
+}
+
+/// Failure modes surfaced to the frontend as structured values instead of
+/// answer-shaped strings, so the UI can tell an error from a real response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DevCaptionError {
+    /// The Gemini call failed: an HTTP/API error, an unparseable body, or a
+    /// network failure. `code` carries the HTTP status when one was received.
+    GeminiFailed { code: Option<u16>, message: String },
+    /// The prompt or the answer was stopped by Gemini safety filters.
+    Blocked { reason: String },
+    /// The request exceeded the configured deadline before completing.
+    Timeout { message: String },
+}
+
+impl std::fmt::Display for DevCaptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DevCaptionError::GeminiFailed { code: Some(code), message } => {
+                write!(f, "Gemini request failed (HTTP {}): {}", code, message)
+            }
+            DevCaptionError::GeminiFailed { code: None, message } => {
+                write!(f, "Gemini request failed: {}", message)
+            }
+            DevCaptionError::Blocked { reason } => write!(f, "Blocked: {}", reason),
+            DevCaptionError::Timeout { message } => write!(f, "Gemini request timed out: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for DevCaptionError {}
+
+/// Interpret a raw Gemini response body. The body is parsed into a
+/// permissive `serde_json::Value` and picked apart defensively: the API
+/// keeps growing new shapes (promptFeedback-only bodies, candidates without
+/// parts), and strict typed parsing turned every novel shape into one
+/// generic "could not parse" error.
+fn interpret_response_body(
+    body: &str,
+    status_code: u16,
+) -> Result<(String, Option<GeminiUsage>), DevCaptionError> {
+    let value: serde_json::Value = serde_json::from_str(body).map_err(|e| {
+        DevCaptionError::GeminiFailed {
+            code: Some(status_code),
+            message: format!("could not parse response body: {}", e),
+        }
+    })?;
+
+    // An explicit API error object wins over everything else
+    if let Some(error) = value.get("error") {
+        let message = error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or("unspecified API error")
+            .to_string();
+        error!("API Error: {} ({})", message, error.get("code").and_then(|c| c.as_u64()).unwrap_or(0));
+        return Err(DevCaptionError::GeminiFailed {
+            code: Some(status_code),
+            message,
+        });
+    }
+
+    let usage = value
+        .get("usageMetadata")
+        .and_then(|u| serde_json::from_value::<GeminiUsage>(u.clone()).ok());
+    if usage.is_none() {
+        info!("Gemini response carried no usageMetadata block");
+    }
+
+    let candidates = value.get("candidates").and_then(|c| c.as_array());
+    if let Some(candidate) = candidates.and_then(|c| c.first()) {
+        // A candidate stopped by the safety filter carries a finishReason
+        // and usually no content - surface that distinctly instead of the
+        // generic fallback message
+        if candidate.get("finishReason").and_then(|r| r.as_str()) == Some("SAFETY") {
+            error!("Gemini candidate blocked by safety filter");
+            return Err(DevCaptionError::Blocked {
+                reason: "answer was stopped by Gemini safety filters (finishReason=SAFETY)".to_string(),
+            });
+        }
+
+        if let Some(text) = candidate.pointer("/content/parts/0/text").and_then(|t| t.as_str()) {
+            return Ok((text.to_string(), usage));
+        }
+        return Ok(("No response content available.".to_string(), usage));
+    }
+
+    // No usable candidates: a promptFeedback block means the prompt itself
+    // was rejected (these bodies often carry an empty candidates array too)
+    if let Some(feedback) = value.get("promptFeedback") {
+        let reason = feedback
+            .get("blockReason")
+            .and_then(|r| r.as_str())
+            .unwrap_or("unspecified")
+            .to_string();
+        error!("Gemini blocked the prompt: {}", reason);
+        return Err(DevCaptionError::Blocked {
+            reason: format!("prompt was rejected by Gemini safety filters ({})", reason),
+        });
+    }
+
+    if candidates.is_some() {
+        return Err(DevCaptionError::GeminiFailed {
+            code: Some(status_code),
+            message: "response contained no candidates".to_string(),
+        });
+    }
+
+    let keys: Vec<&String> = value.as_object().map(|o| o.keys().collect()).unwrap_or_default();
+    error!("Unrecognized Gemini response shape (top-level keys: {:?})", keys);
+    Err(DevCaptionError::GeminiFailed {
+        code: Some(status_code),
+        message: format!("unrecognized response shape (top-level keys: {:?})", keys),
+    })
+}
+
+/// A failure worth retrying: the request never got an HTTP response, so
+/// resending it can't double anything on the server side.
+/// Distinct failure classes for the connectivity probe, so the UI can say
+/// "key rejected" or "quota exhausted" instead of a generic error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GeminiKeyStatus {
+    Valid,
+    InvalidKey,
+    QuotaExhausted,
+    NetworkError,
+    ApiError,
+}
+
+/// Result of the connectivity probe: what it found and how long the round
+/// trip took.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeminiStatus {
+    pub status: GeminiKeyStatus,
+    pub latency_ms: u64,
+    pub detail: Option<String>,
+}
+
+/// Map a probe response onto a key status. A rejected key surfaces either
+/// as 401/403 or as a 400 carrying API_KEY_INVALID, depending on the
+/// endpoint flavor.
+fn classify_probe_response(code: u16, body: &str) -> GeminiKeyStatus {
+    match code {
+        200..=299 => GeminiKeyStatus::Valid,
+        401 | 403 => GeminiKeyStatus::InvalidKey,
+        400 if body.contains("API_KEY_INVALID") => GeminiKeyStatus::InvalidKey,
+        429 => GeminiKeyStatus::QuotaExhausted,
+        _ => GeminiKeyStatus::ApiError,
+    }
+}
+
+fn is_transient(error: &DevCaptionError) -> bool {
+    matches!(
+        error,
+        DevCaptionError::Timeout { .. } | DevCaptionError::GeminiFailed { code: None, .. }
+    )
+}
+
+/// Character budget per summarization chunk; roughly 3k tokens, well inside
+/// the model's context while keeping the number of API calls small.
+const SUMMARY_CHUNK_CHARS: usize = 12_000;
+
+/// Split a transcript into whitespace-aligned chunks of at most
+/// `SUMMARY_CHUNK_CHARS` characters for map-reduce summarization.
+fn split_for_summary(transcript: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    for word in transcript.split_whitespace() {
+        if !current.is_empty() && current.len() + word.len() + 1 > SUMMARY_CHUNK_CHARS {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+/// Keyword lists driving `classify_question`, kept as data so they can be
+/// swapped from the frontend. The defaults cover English and Portuguese,
+/// the two languages the app actually gets used in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestionKeywords {
+    pub greetings: Vec<String>,
+    pub technical: Vec<String>,
+}
+
+impl Default for QuestionKeywords {
+    fn default() -> Self {
         Self {
-            api_key,
-            base_url: String::from("https://generativelanguage.googleapis.com/v1beta/models/gemini-2.0-flash:generateContent"),
-            context,
+            greetings: [
+                "how are you", "good morning", "hello",
+                "tudo bem", "bom dia", "boa tarde", "olá",
+            ].iter().map(|s| s.to_string()).collect(),
+            technical: [
+                "react", "javascript", "frontend", "code", "programming",
+                "código", "programação", "desenvolvimento",
+            ].iter().map(|s| s.to_string()).collect(),
         }
     }
+}
 
-    pub async fn get_interview_response(&self, transcription: &str, is_first_question: bool) -> Result<String, Box<dyn std::error::Error>> {
-        info!("Getting interview response for transcription: {}", transcription);
+/// How a transcribed interviewer line should be answered; picks which of
+/// the prompt templates below gets used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuestionKind {
+    Greeting,
+    FirstQuestion,
+    Technical,
+    General,
+}
 
-        let client = reqwest::Client::new();
-        
-        // Base transcription note to include in all prompts
-        let transcription_note = "Note: The question comes from real-time audio transcription, so there might be some noise or repetition in the text. Try to understand the core question even if there are small transcription artifacts.";
-        
-        // Identify question type and adjust response style
-        let is_greeting = transcription.to_lowercase().contains("how are you") || 
-                         transcription.to_lowercase().contains("good morning") ||
-                         transcription.to_lowercase().contains("hello");
-
-        let is_technical = transcription.to_lowercase().contains("react") ||
-                          transcription.to_lowercase().contains("javascript") ||
-                          transcription.to_lowercase().contains("frontend") ||
-                          transcription.to_lowercase().contains("code") ||
-                          transcription.to_lowercase().contains("programming");
-
-        // Construct a context-aware prompt
-        let prompt = if is_greeting {
-            format!(
-                r#"You are me in a frontend engineering job interview. This is a greeting/small talk question.
+/// Who the candidate is in the interview; interpolated into every prompt
+/// template so the tool isn't hardwired to one person's job hunt. The
+/// default matches the original frontend-engineering profile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptProfile {
+    pub role: String,
+    pub company: String,
+    /// Extra free-form guidance appended to every prompt; empty for none.
+    pub guidelines: String,
+}
+
+impl Default for PromptProfile {
+    fn default() -> Self {
+        Self {
+            role: "frontend engineering".to_string(),
+            company: "Grupo SBF".to_string(),
+            guidelines: String::new(),
+        }
+    }
+}
+
+/// Shared preamble warning the model about transcription artifacts.
+const TRANSCRIPTION_NOTE: &str = "Note: The question comes from real-time audio transcription, so there might be some noise or repetition in the text. Try to understand the core question even if there are small transcription artifacts.";
+
+/// Classify a transcribed question so the right prompt template is used.
+/// Greetings win over everything (a "hello, shall we talk React?" opener
+/// still deserves a short greeting back); the first-question template only
+/// applies when nothing more specific matched.
+pub fn classify_question(text: &str, is_first_question: bool, keywords: &QuestionKeywords) -> QuestionKind {
+    let lower = text.to_lowercase();
+
+    let is_greeting = keywords.greetings
+        .iter()
+        .any(|keyword| !keyword.is_empty() && lower.contains(&keyword.to_lowercase()));
+    if is_greeting {
+        return QuestionKind::Greeting;
+    }
+
+    if is_first_question {
+        return QuestionKind::FirstQuestion;
+    }
+
+    let is_technical = keywords.technical
+        .iter()
+        .any(|keyword| !keyword.is_empty() && lower.contains(&keyword.to_lowercase()));
+    if is_technical {
+        QuestionKind::Technical
+    } else {
+        QuestionKind::General
+    }
+}
+
+/// Build the full prompt for one question from its classification, the
+/// candidate background, and the transcribed question itself.
+pub fn build_prompt(kind: QuestionKind, context: &str, question: &str, profile: &PromptProfile) -> String {
+    let mut prompt = match kind {
+        QuestionKind::Greeting => format!(
+            r#"You are me in a {role} job interview. This is a greeting/small talk question.
 
 {transcription_note}
 
 The interviewer says: "{question}"
 
 Respond naturally but professionally. Keep it very brief and simple - just answer the greeting without volunteering too much information. Save the details about my background for when they actually ask about it."#,
-                transcription_note = transcription_note,
-                question = transcription
-            )
-        } else if is_first_question && !is_greeting {
-            format!(
-                r#"You are me in a frontend engineering job interview. Use this information about me to answer questions naturally:
+            role = profile.role,
+            transcription_note = TRANSCRIPTION_NOTE,
+            question = question
+        ),
+        QuestionKind::FirstQuestion => format!(
+            r#"You are me in a {role} job interview. Use this information about me to answer questions naturally:
 
 {context}
 
@@ -105,13 +552,13 @@ Important guidelines:
 6. If it's a technical question, show expertise but remain humble
 7. If it's about my background, focus on relevant experience for the role
 8. If the question has transcription artifacts, focus on the main intent"#,
-                context = self.context,
-                transcription_note = transcription_note,
-                question = transcription
-            )
-        } else if is_technical {
-            format!(
-                r#"You are me in a frontend engineering job interview. Here's my background:
+            role = profile.role,
+            context = context,
+            transcription_note = TRANSCRIPTION_NOTE,
+            question = question
+        ),
+        QuestionKind::Technical => format!(
+            r#"You are me in a {role} job interview. Here's my background:
 
 {context}
 
@@ -121,19 +568,20 @@ The interviewer asks this technical question: "{question}"
 
 Guidelines for technical response:
 1. Show practical experience, not just theoretical knowledge
-2. Use specific examples from my work at Grupo SBF or previous roles
+2. Use specific examples from my work at {company} or previous roles
 3. Demonstrate both technical depth and UX awareness
 4. Be confident but not arrogant
 5. Focus on real-world application and problem-solving
 6. Keep the response focused and structured
 7. If the question has transcription noise, address the core technical concept"#,
-                context = self.context,
-                transcription_note = transcription_note,
-                question = transcription
-            )
-        } else {
-            format!(
-                r#"You are me in a frontend engineering job interview. You have my background:
+            role = profile.role,
+            company = profile.company,
+            context = context,
+            transcription_note = TRANSCRIPTION_NOTE,
+            question = question
+        ),
+        QuestionKind::General => format!(
+            r#"You are me in a {role} job interview. You have my background:
 
 {context}
 
@@ -148,64 +596,557 @@ Remember:
 4. Don't volunteer unrelated information
 5. Be authentic but professional
 6. If there's transcription noise, focus on the clear parts of the question"#,
-                context = self.context,
-                transcription_note = transcription_note,
-                question = transcription
-            )
+            role = profile.role,
+            context = context,
+            transcription_note = TRANSCRIPTION_NOTE,
+            question = question
+        ),
+    };
+
+    if !profile.guidelines.trim().is_empty() {
+        prompt.push_str("\n\nAdditional guidance from my profile:\n");
+        prompt.push_str(profile.guidelines.trim());
+    }
+
+    prompt
+}
+
+pub struct GeminiService {
+    api_key: String,
+    base_url: String,
+    context: String,
+    safety_threshold: String,
+    client: reqwest::Client,
+    cleaner: ResponseCleaner,
+    keywords: QuestionKeywords,
+    profile: PromptProfile,
+    brevity: AnswerBrevity,
+    max_retries: u32,
+    retry_notifier: Option<Box<dyn Fn(u32) + Send + Sync>>,
+}
+
+fn build_client(timeout: Duration, connect_timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .connect_timeout(connect_timeout)
+        .build()
+        .unwrap_or_else(|e| {
+            error!("Failed to build HTTP client with timeouts, falling back to defaults: {}", e);
+            reqwest::Client::new()
+        })
+}
+
+impl GeminiService {
+    pub fn new(api_key: String, context: String) -> Self {
+        Self {
+            api_key,
+            base_url: String::from("https://generativelanguage.googleapis.com/v1beta/models/gemini-2.0-flash:generateContent"),
+            context,
+            safety_threshold: DEFAULT_SAFETY_THRESHOLD.to_string(),
+            client: build_client(
+                Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+                Duration::from_secs(DEFAULT_CONNECT_TIMEOUT_SECS),
+            ),
+            cleaner: ResponseCleaner::default(),
+            keywords: QuestionKeywords::default(),
+            profile: PromptProfile::default(),
+            brevity: AnswerBrevity::default(),
+            max_retries: DEFAULT_TRANSIENT_RETRIES,
+            retry_notifier: None,
+        }
+    }
+
+    /// How many times to retry a transiently-dropped request.
+    pub fn set_max_retries(&mut self, retries: u32) {
+        self.max_retries = retries;
+    }
+
+    /// Called with the attempt number before each retry, so the UI can show
+    /// a "reconnecting" state instead of appearing hung.
+    pub fn set_retry_notifier(&mut self, notifier: Box<dyn Fn(u32) + Send + Sync>) {
+        self.retry_notifier = Some(notifier);
+    }
+
+    /// Override who the candidate is in the prompt templates.
+    pub fn set_profile(&mut self, profile: PromptProfile) {
+        self.profile = profile;
+    }
+
+    /// Choose how long interview answers should run.
+    pub fn set_brevity(&mut self, brevity: AnswerBrevity) {
+        self.brevity = brevity;
+    }
+
+    /// Override the classification keyword lists.
+    pub fn set_keywords(&mut self, keywords: QuestionKeywords) {
+        self.keywords = keywords;
+    }
+
+    /// Override the post-processing rules applied to answers.
+    pub fn set_cleaner(&mut self, cleaner: ResponseCleaner) {
+        self.cleaner = cleaner;
+    }
+
+    /// Rebuild the shared HTTP client with new total and connect deadlines.
+    pub fn set_timeouts(&mut self, timeout_secs: u64, connect_timeout_secs: u64) {
+        self.client = build_client(
+            Duration::from_secs(timeout_secs),
+            Duration::from_secs(connect_timeout_secs),
+        );
+    }
+
+    /// Point requests at a different endpoint (proxy, Vertex AI regional
+    /// host, or a local mock server). The `key` query parameter and request
+    /// body are unaffected.
+    pub fn set_base_url(&mut self, base_url: String) {
+        self.base_url = base_url;
+    }
+
+    /// Override the safety threshold applied to every harm category.
+    /// Must be one of `VALID_SAFETY_THRESHOLDS`.
+    pub fn set_safety_threshold(&mut self, threshold: String) {
+        self.safety_threshold = threshold;
+    }
+
+    fn safety_settings(&self) -> Vec<SafetySetting> {
+        SAFETY_CATEGORIES.iter()
+            .map(|category| SafetySetting {
+                category: category.to_string(),
+                threshold: self.safety_threshold.clone(),
+            })
+            .collect()
+    }
+
+    /// Classification plus prompt construction, shared by the real call and
+    /// the dry-run preview so the two can never drift apart.
+    fn compose_interview_prompt(&self, transcription: &str, is_first_question: bool) -> String {
+        let kind = classify_question(transcription, is_first_question, &self.keywords);
+        let mut prompt = build_prompt(kind, &self.context, transcription, &self.profile);
+        if let Some(instruction) = self.brevity.instruction() {
+            prompt.push_str("\n\n");
+            prompt.push_str(instruction);
+        }
+        prompt
+    }
+
+    /// The exact prompt `get_interview_response` would send for this
+    /// transcription - classification, templates, profile and brevity
+    /// instruction included - without calling the API. For iterating on
+    /// persona and guidelines without spending quota.
+    pub fn preview_interview_prompt(&self, transcription: &str, is_first_question: bool) -> String {
+        self.compose_interview_prompt(transcription, is_first_question)
+    }
+
+    pub async fn get_interview_response(&self, transcription: &str, is_first_question: bool) -> Result<InterviewResponse, DevCaptionError> {
+        info!("Getting interview response for transcription: {}", transcription);
+
+        let prompt = self.compose_interview_prompt(transcription, is_first_question);
+
+        let (raw_text, usage) = self
+            .send_prompt(prompt, Some(self.brevity.max_output_tokens()))
+            .await?;
+
+        info!("Successfully got response from Gemini");
+        Ok(InterviewResponse {
+            text: self.cleaner.clean(&raw_text),
+            usage,
+        })
+    }
+
+    /// Summarize a whole session transcript into key points and action
+    /// items. Long transcripts are split into chunks, each chunk summarized
+    /// on its own, and the partial summaries merged in a final pass.
+    pub async fn summarize(&self, transcript: &str) -> Result<String, DevCaptionError> {
+        let chunks = split_for_summary(transcript);
+
+        if chunks.len() <= 1 {
+            let prompt = format!(
+                "Summarize this meeting transcript. List the key points discussed and any action items (with who owns them, when stated). The text comes from real-time transcription, so ignore small artifacts.\n\nTranscript:\n{}",
+                transcript
+            );
+            let (summary, _) = self.send_prompt(prompt, None).await?;
+            return Ok(summary.trim().to_string());
+        }
+
+        info!("Transcript too long for one pass, summarizing {} chunks", chunks.len());
+
+        let mut partials = Vec::new();
+        for (index, chunk) in chunks.iter().enumerate() {
+            let prompt = format!(
+                "This is part {} of {} of a meeting transcript. Summarize just this part: key points, decisions, and action items. The text comes from real-time transcription, so ignore small artifacts.\n\nTranscript part:\n{}",
+                index + 1,
+                chunks.len(),
+                chunk
+            );
+            let (partial, _) = self.send_prompt(prompt, None).await?;
+            partials.push(partial);
+        }
+
+        let prompt = format!(
+            "These are partial summaries of consecutive parts of one meeting. Merge them into a single summary with the key points discussed and any action items, without repeating yourself.\n\nPartial summaries:\n{}",
+            partials.join("\n\n")
+        );
+        let (summary, _) = self.send_prompt(prompt, None).await?;
+        Ok(summary.trim().to_string())
+    }
+
+    /// Probe the API with a one-token request: cheap enough to wire to a
+    /// "Test connection" button, and it exercises the exact client
+    /// configuration (key, base URL, timeouts) that real requests use.
+    /// Failures come back as a status, never an Err - an unreachable host
+    /// is a finding, not a fault.
+    pub async fn verify_key(&self) -> GeminiStatus {
+        let request = GeminiRequest {
+            contents: vec![Content {
+                parts: vec![Part {
+                    text: "ping".to_string(),
+                }],
+            }],
+            safety_settings: self.safety_settings(),
+            generation_config: Some(GenerationConfig { max_output_tokens: 1 }),
         };
 
+        let started = Instant::now();
+        let response = self.client
+            .post(&self.base_url)
+            .query(&[("key", &self.api_key)])
+            .json(&request)
+            .send()
+            .await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        match response {
+            Err(e) => GeminiStatus {
+                status: GeminiKeyStatus::NetworkError,
+                latency_ms,
+                detail: Some(e.to_string()),
+            },
+            Ok(response) => {
+                let code = response.status().as_u16();
+                let body = response.text().await.unwrap_or_default();
+                let status = classify_probe_response(code, &body);
+
+                info!("Gemini probe: HTTP {} in {}ms -> {:?}", code, latency_ms, status);
+
+                let detail = match status {
+                    GeminiKeyStatus::Valid => None,
+                    _ => Some(format!("HTTP {}", code)),
+                };
+                GeminiStatus { status, latency_ms, detail }
+            }
+        }
+    }
+
+    /// Send one prompt to Gemini, retrying transient drops (timeouts and
+    /// status-less network failures) up to `max_retries` times with a short
+    /// backoff. API errors that carry an HTTP status are never retried.
+    async fn send_prompt(
+        &self,
+        prompt: String,
+        max_output_tokens: Option<u32>,
+    ) -> Result<(String, Option<GeminiUsage>), DevCaptionError> {
+        let mut attempt = 0;
+        loop {
+            match self.send_prompt_once(prompt.clone(), max_output_tokens).await {
+                Err(e) if attempt < self.max_retries && is_transient(&e) => {
+                    attempt += 1;
+                    warn!("Transient Gemini failure ({}), retry {}/{}", e, attempt, self.max_retries);
+                    if let Some(notifier) = &self.retry_notifier {
+                        notifier(attempt);
+                    }
+                    tokio::time::sleep(Duration::from_millis(500 * attempt as u64)).await;
+                }
+                other => return other,
+            }
+        }
+    }
+
+    /// Send one prompt to Gemini and return the raw answer text plus token
+    /// usage. Callers decide how much cleanup the text needs.
+    async fn send_prompt_once(
+        &self,
+        prompt: String,
+        max_output_tokens: Option<u32>,
+    ) -> Result<(String, Option<GeminiUsage>), DevCaptionError> {
         let request = GeminiRequest {
             contents: vec![Content {
                 parts: vec![Part {
                     text: prompt,
                 }],
             }],
+            safety_settings: self.safety_settings(),
+            generation_config: max_output_tokens
+                .map(|tokens| GenerationConfig { max_output_tokens: tokens }),
         };
 
         // Send request and get raw response first
-        let response = client
+        let response = self.client
             .post(&self.base_url)
             .query(&[("key", &self.api_key)])
             .json(&request)
             .send()
-            .await?;
+            .await
+            .map_err(|e| {
+                if e.is_timeout() {
+                    DevCaptionError::Timeout { message: e.to_string() }
+                } else {
+                    DevCaptionError::GeminiFailed {
+                        code: e.status().map(|s| s.as_u16()),
+                        message: e.to_string(),
+                    }
+                }
+            })?;
 
         // Get the response status and text
         let status = response.status();
-        let response_text = response.text().await?;
+        let response_text = response.text().await
+            .map_err(|e| DevCaptionError::GeminiFailed {
+                code: Some(status.as_u16()),
+                message: e.to_string(),
+            })?;
         
         info!("API Response Status: {}", status);
         info!("API Response Body: {}", response_text);
 
-        // Try to parse the response
-        match serde_json::from_str::<GeminiResponse>(&response_text) {
-            Ok(GeminiResponse::Success { candidates }) => {
-                if let Some(candidate) = candidates.first() {
-                    if let Some(part) = candidate.content.parts.first() {
-                        // Clean up the response
-                        let cleaned_response = part.text
-                            .replace("[Key Points]", "")
-                            .replace("[Response]", "")
-                            .replace("Thank you for your question.", "")
-                            .replace("That's a great question.", "")
-                            .replace("Thank you for asking.", "")
-                            .trim()
-                            .to_string();
-                        
-                        info!("Successfully got response from Gemini");
-                        return Ok(cleaned_response);
-                    }
-                }
-                Ok("No response content available.".to_string())
-            }
-            Ok(GeminiResponse::Error { error }) => {
-                error!("API Error: {} ({})", error.message, error.code);
-                Ok(format!("Error: {}", error.message))
+        interpret_response_body(&response_text, status.as_u16())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probe_classification_separates_key_quota_and_api_errors() {
+        assert_eq!(classify_probe_response(200, "{}"), GeminiKeyStatus::Valid);
+        assert_eq!(classify_probe_response(401, ""), GeminiKeyStatus::InvalidKey);
+        assert_eq!(
+            classify_probe_response(400, r#"{"error":{"details":[{"reason":"API_KEY_INVALID"}]}}"#),
+            GeminiKeyStatus::InvalidKey
+        );
+        assert_eq!(classify_probe_response(400, "malformed request"), GeminiKeyStatus::ApiError);
+        assert_eq!(classify_probe_response(429, ""), GeminiKeyStatus::QuotaExhausted);
+        assert_eq!(classify_probe_response(503, ""), GeminiKeyStatus::ApiError);
+    }
+
+    #[test]
+    fn cleaner_strips_markdown_openers_and_trailing_offers() {
+        let cleaner = ResponseCleaner::default();
+        let noisy = "Sure, here's my answer.\n\n## My Experience\n\n- I led the **checkout** rewrite\n- I mentor `junior` engineers\n\nLet me know if you'd like more detail!";
+
+        let cleaned = cleaner.clean(noisy);
+        assert_eq!(cleaned, "here's my answer.\n\nMy Experience\n\nI led the checkout rewrite\nI mentor junior engineers");
+    }
+
+    #[test]
+    fn cleaner_removes_legacy_phrases() {
+        let cleaner = ResponseCleaner::default();
+        assert_eq!(
+            cleaner.clean("That's a great question. I use React daily."),
+            "I use React daily."
+        );
+    }
+
+    #[test]
+    fn cleaner_keeps_markdown_when_disabled() {
+        let cleaner = ResponseCleaner { strip_markdown: false, ..Default::default() };
+        assert_eq!(cleaner.clean("**bold** claim"), "**bold** claim");
+    }
+
+    #[test]
+    fn greeting_wins_over_other_classifications() {
+        let keywords = QuestionKeywords::default();
+        assert_eq!(classify_question("Hello! Ready to talk about React?", true, &keywords), QuestionKind::Greeting);
+        assert_eq!(classify_question("good morning", false, &keywords), QuestionKind::Greeting);
+    }
+
+    #[test]
+    fn first_question_applies_only_without_greeting() {
+        let keywords = QuestionKeywords::default();
+        assert_eq!(classify_question("Tell me about yourself", true, &keywords), QuestionKind::FirstQuestion);
+        assert_eq!(classify_question("Tell me about yourself", false, &keywords), QuestionKind::General);
+    }
+
+    #[test]
+    fn technical_keywords_select_the_technical_template() {
+        let keywords = QuestionKeywords::default();
+        assert_eq!(classify_question("How do you structure a React app?", false, &keywords), QuestionKind::Technical);
+        assert_eq!(classify_question("What motivates you?", false, &keywords), QuestionKind::General);
+    }
+
+    #[test]
+    fn portuguese_defaults_classify_portuguese_questions() {
+        let keywords = QuestionKeywords::default();
+        assert_eq!(classify_question("Bom dia, tudo bem?", false, &keywords), QuestionKind::Greeting);
+        assert_eq!(classify_question("Como você organiza o código de um projeto grande?", false, &keywords), QuestionKind::Technical);
+    }
+
+    #[test]
+    fn custom_keyword_lists_replace_the_defaults() {
+        let keywords = QuestionKeywords {
+            greetings: vec!["guten morgen".to_string()],
+            technical: vec!["rust".to_string()],
+        };
+        assert_eq!(classify_question("Guten Morgen!", false, &keywords), QuestionKind::Greeting);
+        assert_eq!(classify_question("Why Rust?", false, &keywords), QuestionKind::Technical);
+        assert_eq!(classify_question("hello", false, &keywords), QuestionKind::General);
+    }
+
+    #[test]
+    fn empty_text_classifies_as_general() {
+        assert_eq!(classify_question("", false, &QuestionKeywords::default()), QuestionKind::General);
+    }
+
+    #[test]
+    fn built_prompts_embed_question_and_context() {
+        let profile = PromptProfile::default();
+        let prompt = build_prompt(QuestionKind::Technical, "my background", "why hooks?", &profile);
+        assert!(prompt.contains("my background"));
+        assert!(prompt.contains("why hooks?"));
+        assert!(prompt.contains(TRANSCRIPTION_NOTE));
+        assert!(prompt.contains("frontend engineering job interview"));
+        assert!(prompt.contains("Grupo SBF"));
+
+        // The greeting template deliberately omits the background
+        let greeting = build_prompt(QuestionKind::Greeting, "my background", "hello there", &profile);
+        assert!(!greeting.contains("my background"));
+    }
+
+    #[test]
+    fn custom_profile_replaces_role_company_and_adds_guidelines() {
+        let profile = PromptProfile {
+            role: "backend engineering".to_string(),
+            company: "Acme Corp".to_string(),
+            guidelines: "Prefer Go examples.".to_string(),
+        };
+
+        let prompt = build_prompt(QuestionKind::Technical, "ctx", "how do you scale?", &profile);
+        assert!(prompt.contains("backend engineering job interview"));
+        assert!(prompt.contains("Acme Corp"));
+        assert!(prompt.contains("Prefer Go examples."));
+        assert!(!prompt.contains("Grupo SBF"));
+    }
+
+    #[test]
+    fn short_transcripts_stay_in_one_summary_chunk() {
+        let chunks = split_for_summary("we agreed to ship the beta on friday");
+        assert_eq!(chunks.len(), 1);
+    }
+
+    #[test]
+    fn long_transcripts_split_on_word_boundaries() {
+        let transcript = "word ".repeat(10_000);
+        let chunks = split_for_summary(&transcript);
+
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(chunk.len() <= SUMMARY_CHUNK_CHARS);
+            assert!(!chunk.starts_with(' ') && !chunk.ends_with(' '));
+        }
+        let total_words: usize = chunks.iter().map(|c| c.split_whitespace().count()).sum();
+        assert_eq!(total_words, 10_000);
+    }
+
+    #[test]
+    fn only_status_less_failures_count_as_transient() {
+        assert!(is_transient(&DevCaptionError::Timeout { message: "deadline".to_string() }));
+        assert!(is_transient(&DevCaptionError::GeminiFailed { code: None, message: "reset".to_string() }));
+        assert!(!is_transient(&DevCaptionError::GeminiFailed { code: Some(500), message: "boom".to_string() }));
+        assert!(!is_transient(&DevCaptionError::Blocked { reason: "safety".to_string() }));
+    }
+
+    #[test]
+    fn gemini_failed_display_includes_http_status() {
+        let error = DevCaptionError::GeminiFailed {
+            code: Some(429),
+            message: "quota exceeded".to_string(),
+        };
+        assert_eq!(error.to_string(), "Gemini request failed (HTTP 429): quota exceeded");
+
+        let no_status = DevCaptionError::GeminiFailed {
+            code: None,
+            message: "connection reset".to_string(),
+        };
+        assert_eq!(no_status.to_string(), "Gemini request failed: connection reset");
+    }
+
+    #[test]
+    fn brevity_levels_order_token_caps_and_only_extremes_instruct() {
+        assert!(AnswerBrevity::Terse.max_output_tokens() < AnswerBrevity::Normal.max_output_tokens());
+        assert!(AnswerBrevity::Normal.max_output_tokens() < AnswerBrevity::Detailed.max_output_tokens());
+
+        assert!(AnswerBrevity::Terse.instruction().unwrap().contains("sentences"));
+        assert!(AnswerBrevity::Normal.instruction().is_none());
+        assert!(AnswerBrevity::Detailed.instruction().is_some());
+
+        assert_eq!(AnswerBrevity::parse("terse"), Some(AnswerBrevity::Terse));
+        assert_eq!(AnswerBrevity::parse("verbose"), None);
+    }
+
+    #[test]
+    fn interprets_successful_response_fixture() {
+        let fixture = r#"{"candidates":[{"content":{"parts":[{"text":"Use hooks."}]},"finishReason":"STOP"}],"usageMetadata":{"promptTokenCount":10,"candidatesTokenCount":3,"totalTokenCount":13}}"#;
+
+        let (text, usage) = interpret_response_body(fixture, 200).expect("fixture should succeed");
+        assert_eq!(text, "Use hooks.");
+        assert_eq!(usage.expect("usage should parse").total_tokens, 13);
+    }
+
+    #[test]
+    fn interprets_blocked_prompt_fixture() {
+        let fixture = r#"{"promptFeedback":{"blockReason":"SAFETY","safetyRatings":[]}}"#;
+
+        match interpret_response_body(fixture, 200) {
+            Err(DevCaptionError::Blocked { reason }) => assert!(reason.contains("SAFETY")),
+            other => panic!("expected Blocked, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn interprets_safety_stopped_candidate_fixture() {
+        let fixture = r#"{"candidates":[{"finishReason":"SAFETY","index":0}]}"#;
+
+        match interpret_response_body(fixture, 200) {
+            Err(DevCaptionError::Blocked { reason }) => assert!(reason.contains("finishReason=SAFETY")),
+            other => panic!("expected Blocked, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn empty_candidates_defer_to_prompt_feedback_or_error_precisely() {
+        // Blocked prompts often ship an empty candidates array alongside
+        // the feedback block - the feedback is the real story
+        let fixture = r#"{"candidates":[],"promptFeedback":{"blockReason":"OTHER"}}"#;
+        match interpret_response_body(fixture, 200) {
+            Err(DevCaptionError::Blocked { reason }) => assert!(reason.contains("OTHER")),
+            other => panic!("expected Blocked, got {:?}", other),
+        }
+
+        // Without feedback the error still names the actual problem
+        match interpret_response_body(r#"{"candidates":[]}"#, 200) {
+            Err(DevCaptionError::GeminiFailed { message, .. }) => {
+                assert!(message.contains("no candidates"));
             }
-            Err(e) => {
-                error!("Failed to parse response: {}", e);
-                Ok("Failed to process the response. Please try again.".to_string())
+            other => panic!("expected GeminiFailed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn candidate_without_parts_yields_the_fallback_text() {
+        let fixture = r#"{"candidates":[{"content":{"parts":[]},"finishReason":"STOP"}]}"#;
+
+        let (text, usage) = interpret_response_body(fixture, 200).expect("odd but valid shape");
+        assert_eq!(text, "No response content available.");
+        assert!(usage.is_none());
+    }
+
+    #[test]
+    fn unrecognized_shapes_report_their_top_level_keys() {
+        match interpret_response_body(r#"{"modelVersion":"gemini-x"}"#, 200) {
+            Err(DevCaptionError::GeminiFailed { message, .. }) => {
+                assert!(message.contains("modelVersion"));
             }
+            other => panic!("expected GeminiFailed, got {:?}", other),
         }
     }
-} 
\ No newline at end of file
+}
\ No newline at end of file
@@ -0,0 +1,142 @@
+//! In-memory ring buffer for log output so the frontend can show recent
+//! lines without a terminal. Installed as the global `log` sink; every line
+//! is still mirrored to stderr so terminal users lose nothing.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+
+/// How many lines the ring keeps before the oldest are dropped.
+const LOG_CAPACITY: usize = 1000;
+
+/// One captured log line, shaped for the frontend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogLine {
+    pub level: String,
+    pub timestamp: u64,
+    pub target: String,
+    pub message: String,
+}
+
+// Plain `lock()` with a soft failure path on purpose: the logger is called
+// from everywhere, including lock-recovery warnings, so it must never log
+// or panic itself.
+static LOG_BUFFER: Mutex<VecDeque<LogLine>> = Mutex::new(VecDeque::new());
+
+/// Callback invoked for every error-level line, used by `lib.rs` to forward
+/// errors to the frontend as events.
+type ErrorSink = Box<dyn Fn(LogLine) + Send + Sync>;
+
+static ERROR_SINK: Mutex<Option<ErrorSink>> = Mutex::new(None);
+
+struct RingLogger;
+
+impl Log for RingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let line = LogLine {
+            level: record.level().to_string(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+
+        eprintln!("[{}] {}: {}", line.level, line.target, line.message);
+
+        if record.level() == Level::Error {
+            if let Ok(sink) = ERROR_SINK.lock() {
+                if let Some(sink) = sink.as_ref() {
+                    sink(line.clone());
+                }
+            }
+        }
+
+        push_line(line);
+    }
+
+    fn flush(&self) {}
+}
+
+fn push_line(line: LogLine) {
+    if let Ok(mut buffer) = LOG_BUFFER.lock() {
+        if buffer.len() >= LOG_CAPACITY {
+            buffer.pop_front();
+        }
+        buffer.push_back(line);
+    }
+}
+
+static RING_LOGGER: RingLogger = RingLogger;
+
+/// Install the ring logger as the global sink. Safe to call more than once;
+/// `log` only accepts one global logger, so later calls are no-ops.
+pub fn init() {
+    if log::set_logger(&RING_LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+}
+
+/// Register the callback invoked for error-level lines. The callback must
+/// not log, or errors would loop straight back into it.
+pub fn set_error_sink(sink: ErrorSink) {
+    if let Ok(mut slot) = ERROR_SINK.lock() {
+        *slot = Some(sink);
+    }
+}
+
+/// The most recent `limit` captured lines, oldest first.
+pub fn recent(limit: usize) -> Vec<LogLine> {
+    match LOG_BUFFER.lock() {
+        Ok(buffer) => {
+            let skip = buffer.len().saturating_sub(limit);
+            buffer.iter().skip(skip).cloned().collect()
+        }
+        Err(_) => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(message: &str) -> LogLine {
+        LogLine {
+            level: "INFO".to_string(),
+            timestamp: 0,
+            target: "test".to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    // One test on purpose: the buffer is a process-wide static, so parallel
+    // tests poking at it would race each other.
+    #[test]
+    fn ring_keeps_newest_lines_and_drops_past_capacity() {
+        for i in 0..LOG_CAPACITY + 10 {
+            push_line(line(&format!("line {}", i)));
+        }
+
+        let all = recent(usize::MAX);
+        assert_eq!(all.len(), LOG_CAPACITY);
+        assert_eq!(all.first().unwrap().message, "line 10");
+        assert_eq!(all.last().unwrap().message, format!("line {}", LOG_CAPACITY + 9));
+
+        let tail = recent(3);
+        assert_eq!(tail.len(), 3);
+        assert_eq!(tail[0].message, format!("line {}", LOG_CAPACITY + 7));
+        assert_eq!(tail[2].message, format!("line {}", LOG_CAPACITY + 9));
+    }
+}
@@ -0,0 +1,210 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use log::{info, warn};
+use tauri::Emitter;
+
+/// The ggml model variants we know how to fetch from the whisper.cpp
+/// repository on Hugging Face.
+pub const MODEL_VARIANTS: [&str; 9] = [
+    "tiny", "tiny.en",
+    "base", "base.en",
+    "small", "small.en",
+    "medium", "medium.en",
+    "large-v3",
+];
+
+const BASE_URL: &str = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
+
+/// Even the tiny model is ~75MB; anything below this is a truncated
+/// download or an HTML error page saved to disk.
+const MIN_MODEL_BYTES: u64 = 10_000_000;
+
+/// How often download progress is pushed to the frontend.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadProgress {
+    pub variant: String,
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+    pub percent: Option<f64>,
+}
+
+/// Where downloaded models live inside the app data dir.
+pub fn models_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("models")
+}
+
+/// The on-disk path a variant would occupy once downloaded.
+pub fn model_path(data_dir: &Path, variant: &str) -> PathBuf {
+    models_dir(data_dir).join(format!("ggml-{}.bin", variant))
+}
+
+/// One installed model found on disk, for the model-picker UI.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledModel {
+    pub variant: String,
+    pub path: String,
+    pub size_bytes: u64,
+    pub multilingual: bool,
+}
+
+/// Every `ggml-*.bin` under the models dir, smallest first so a picker
+/// naturally reads base -> small -> medium -> large. A missing or empty
+/// directory is just an empty list, not an error.
+pub fn list_models(data_dir: &Path) -> Vec<InstalledModel> {
+    let entries = match fs::read_dir(models_dir(data_dir)) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut models = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        if !name.starts_with("ggml-") || !name.ends_with(".bin") {
+            continue;
+        }
+
+        let size_bytes = match entry.metadata() {
+            Ok(meta) => meta.len(),
+            Err(e) => {
+                warn!("Skipping unreadable model file {}: {}", path.display(), e);
+                continue;
+            }
+        };
+
+        let variant = name
+            .trim_start_matches("ggml-")
+            .trim_end_matches(".bin")
+            .to_string();
+        models.push(InstalledModel {
+            // whisper.cpp convention: en-only models carry a ".en" suffix
+            multilingual: !variant.ends_with(".en"),
+            path: path.to_string_lossy().into_owned(),
+            size_bytes,
+            variant,
+        });
+    }
+
+    models.sort_by_key(|model| model.size_bytes);
+    models
+}
+
+fn emit_progress(window: &tauri::Window, variant: &str, downloaded: u64, total: Option<u64>) {
+    let progress = DownloadProgress {
+        variant: variant.to_string(),
+        downloaded_bytes: downloaded,
+        total_bytes: total,
+        percent: total.map(|t| (downloaded as f64 / t as f64 * 100.0).min(100.0)),
+    };
+    if let Err(e) = window.emit("model-download-progress", &progress) {
+        warn!("Failed to emit download progress: {}", e);
+    }
+}
+
+/// Download a ggml model into the app data models dir, resuming a partial
+/// download when one is present. Returns the final model path.
+pub async fn download_model(
+    window: &tauri::Window,
+    variant: &str,
+    data_dir: PathBuf,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if !MODEL_VARIANTS.contains(&variant) {
+        return Err(format!(
+            "Unknown model variant '{}' (expected one of {:?})",
+            variant, MODEL_VARIANTS
+        ).into());
+    }
+
+    let dir = models_dir(&data_dir);
+    fs::create_dir_all(&dir)?;
+
+    let final_path = model_path(&data_dir, variant);
+    if final_path.exists() {
+        info!("Model {} already downloaded: {}", variant, final_path.display());
+        return Ok(final_path.to_string_lossy().into_owned());
+    }
+
+    // Partial downloads accumulate in a .part file and are resumed with a
+    // Range request; only a fully verified file gets the final name
+    let part_path = dir.join(format!("ggml-{}.bin.part", variant));
+    let mut downloaded = if part_path.exists() {
+        fs::metadata(&part_path)?.len()
+    } else {
+        0
+    };
+
+    let url = format!("{}/ggml-{}.bin", BASE_URL, variant);
+    info!("Downloading model {} from {} (resuming at {} bytes)", variant, url, downloaded);
+
+    let client = reqwest::Client::new();
+    let mut request = client.get(&url);
+    if downloaded > 0 {
+        request = request.header("Range", format!("bytes={}-", downloaded));
+    }
+    let mut response = request.send().await?;
+
+    // Resume only counts when the server honored the Range header
+    if downloaded > 0 && response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+        warn!("Server ignored the Range header, restarting download from scratch");
+        downloaded = 0;
+        response = client.get(&url).send().await?;
+    }
+
+    if !response.status().is_success() {
+        return Err(format!("Model download failed: HTTP {}", response.status()).into());
+    }
+
+    let total_bytes = response.content_length().map(|remaining| remaining + downloaded);
+
+    let mut file = if downloaded == 0 {
+        fs::File::create(&part_path)?
+    } else {
+        fs::OpenOptions::new().append(true).open(&part_path)?
+    };
+
+    let mut last_progress = Instant::now();
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk)?;
+        downloaded += chunk.len() as u64;
+
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            emit_progress(window, variant, downloaded, total_bytes);
+        }
+    }
+    file.flush()?;
+    drop(file);
+
+    emit_progress(window, variant, downloaded, total_bytes);
+
+    // Verify before promoting the .part file to its final name
+    let size = fs::metadata(&part_path)?.len();
+    if let Some(expected) = total_bytes {
+        if size != expected {
+            return Err(format!(
+                "Download incomplete: {} of {} bytes (run download_model again to resume)",
+                size, expected
+            ).into());
+        }
+    }
+    if size < MIN_MODEL_BYTES {
+        fs::remove_file(&part_path)?;
+        return Err(format!(
+            "Downloaded file is only {} bytes - not a ggml model, discarded",
+            size
+        ).into());
+    }
+
+    fs::rename(&part_path, &final_path)?;
+    info!("Model {} downloaded to {}", variant, final_path.display());
+
+    Ok(final_path.to_string_lossy().into_owned())
+}
@@ -0,0 +1,181 @@
+use std::fs;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use log::{info, warn};
+
+/// One finished transcription session as kept in the history file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    /// Start wall-clock time in ms since the epoch; doubles as the id.
+    pub id: u64,
+    pub text: String,
+    pub device: Option<String>,
+    pub started_at_ms: u64,
+    pub ended_at_ms: u64,
+    pub segments: Vec<SessionSegment>,
+}
+
+/// A single transcribed chunk with the wall-clock time it was produced.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSegment {
+    pub text: String,
+    pub timestamp_ms: u64,
+    /// Language Whisper detected for this chunk ("en", "pt", ...). Only set
+    /// in auto-detect mode, where code-switching speakers can produce a
+    /// different language per segment. `default` keeps old history files
+    /// readable.
+    #[serde(default)]
+    pub detected_language: Option<String>,
+}
+
+/// Self-contained JSON document for one session: the machine-readable
+/// export for note-taking tools. The metadata travels with the segments so
+/// the file stands alone outside the history store, and an import can
+/// rebuild a `SessionRecord` from it without loss.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionExport {
+    pub metadata: SessionMetadata,
+    pub text: String,
+    pub segments: Vec<SessionSegment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionMetadata {
+    pub device: Option<String>,
+    pub started_at_ms: u64,
+    pub ended_at_ms: u64,
+    /// Whisper model variant loaded at export time, when known; sessions
+    /// don't record which model produced them.
+    pub model: Option<String>,
+    /// Source language setting at export time ("en", or "auto" when
+    /// translate mode detects the language per chunk).
+    pub language: Option<String>,
+}
+
+impl SessionExport {
+    pub fn from_record(record: SessionRecord, model: Option<String>, language: Option<String>) -> Self {
+        Self {
+            metadata: SessionMetadata {
+                device: record.device,
+                started_at_ms: record.started_at_ms,
+                ended_at_ms: record.ended_at_ms,
+                model,
+                language,
+            },
+            text: record.text,
+            segments: record.segments,
+        }
+    }
+
+    /// Rebuild the history record; the start time doubles as the id, same
+    /// as for live sessions.
+    pub fn into_record(self) -> SessionRecord {
+        SessionRecord {
+            id: self.metadata.started_at_ms,
+            text: self.text,
+            device: self.metadata.device,
+            started_at_ms: self.metadata.started_at_ms,
+            ended_at_ms: self.metadata.ended_at_ms,
+            segments: self.segments,
+        }
+    }
+}
+
+/// JSON-file-backed history of finished sessions in the app data dir.
+/// The whole file is rewritten on each mutation - session counts are small
+/// (meeting notes, not telemetry), so simplicity wins over incremental IO.
+pub struct SessionStore {
+    path: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new(data_dir: PathBuf) -> Self {
+        Self {
+            path: data_dir.join("sessions.json"),
+        }
+    }
+
+    fn load(&self) -> Vec<SessionRecord> {
+        match fs::read_to_string(&self.path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Could not parse session history ({}), starting fresh", e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(), // No history yet
+        }
+    }
+
+    fn save_all(&self, sessions: &[SessionRecord]) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(sessions)?)?;
+        Ok(())
+    }
+
+    pub fn append(&self, record: SessionRecord) -> Result<(), Box<dyn std::error::Error>> {
+        let mut sessions = self.load();
+        sessions.push(record);
+        self.save_all(&sessions)?;
+        info!("Session saved to history ({} total)", sessions.len());
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<SessionRecord> {
+        self.load()
+    }
+
+    pub fn get(&self, id: u64) -> Option<SessionRecord> {
+        self.load().into_iter().find(|s| s.id == id)
+    }
+
+    /// Returns true when a session with that id existed and was removed.
+    pub fn delete(&self, id: u64) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut sessions = self.load();
+        let before = sessions.len();
+        sessions.retain(|s| s.id != id);
+
+        if sessions.len() == before {
+            return Ok(false);
+        }
+
+        self.save_all(&sessions)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_export_round_trips_through_json() {
+        let record = SessionRecord {
+            id: 1_700_000_000_000,
+            text: "hello world".to_string(),
+            device: Some("MacBook Pro Microphone".to_string()),
+            started_at_ms: 1_700_000_000_000,
+            ended_at_ms: 1_700_000_090_000,
+            segments: vec![SessionSegment {
+                text: "hello world".to_string(),
+                timestamp_ms: 1_700_000_010_000,
+                detected_language: Some("en".to_string()),
+            }],
+        };
+
+        let export = SessionExport::from_record(record.clone(), Some("base.en".to_string()), Some("en".to_string()));
+        let json = serde_json::to_string_pretty(&export).unwrap();
+        let parsed: SessionExport = serde_json::from_str(&json).unwrap();
+        let rebuilt = parsed.into_record();
+
+        assert_eq!(rebuilt.id, record.id);
+        assert_eq!(rebuilt.text, record.text);
+        assert_eq!(rebuilt.device, record.device);
+        assert_eq!(rebuilt.started_at_ms, record.started_at_ms);
+        assert_eq!(rebuilt.ended_at_ms, record.ended_at_ms);
+        assert_eq!(rebuilt.segments.len(), 1);
+        assert_eq!(rebuilt.segments[0].text, record.segments[0].text);
+        assert_eq!(rebuilt.segments[0].timestamp_ms, record.segments[0].timestamp_ms);
+        assert_eq!(rebuilt.segments[0].detected_language, record.segments[0].detected_language);
+    }
+}
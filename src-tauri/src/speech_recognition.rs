@@ -1,78 +1,276 @@
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
 use log::{info, error, warn};
 use whisper_rs::{WhisperContext, WhisperContextParameters, FullParams, SamplingStrategy};
-use crate::TranscriptionResult;
+use crate::{TranscriptionResult, WordConfidence};
+
+/// How Whisper samples tokens. Greedy is fast (good for streaming partials),
+/// beam search is slower but more accurate (good for the final chunk).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplingMode {
+    Greedy,
+    BeamSearch { beam_size: i32 },
+}
+
+impl SamplingMode {
+    fn to_strategy(self) -> SamplingStrategy {
+        match self {
+            SamplingMode::Greedy => SamplingStrategy::Greedy { best_of: 1 },
+            SamplingMode::BeamSearch { beam_size } => SamplingStrategy::BeamSearch {
+                beam_size,
+                patience: -1.0, // whisper.cpp default
+            },
+        }
+    }
+}
+
+/// Whisper decoding context flags for one kind of chunk. `no_context`
+/// stops the decoder from conditioning on its previous output and
+/// `single_segment` forces everything into one segment - both are right for
+/// tiny streaming windows (less cross-chunk hallucination, lower latency)
+/// but cost accuracy on long final chunks, where cross-segment context
+/// helps the decoder.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ContextFlags {
+    pub no_context: bool,
+    pub single_segment: bool,
+}
+
+impl ContextFlags {
+    /// Default for streaming partials: decode each window in isolation.
+    pub fn isolated() -> Self {
+        Self { no_context: true, single_segment: true }
+    }
+
+    /// Default for final and file transcriptions: keep full context.
+    pub fn contextual() -> Self {
+        Self { no_context: false, single_segment: false }
+    }
+}
+
+/// Metadata about the currently loaded model, for UI display ("Model:
+/// base.en (CPU)") and to hide the language selector for en-only models.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub variant: String,
+    pub path: String,
+    pub multilingual: bool,
+    pub gpu: bool,
+}
+
+/// "models/ggml-base.en.bin" -> "base.en"
+fn variant_from_path(path: &str) -> String {
+    std::path::Path::new(path)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(path)
+        .trim_start_matches("ggml-")
+        .to_string()
+}
 
 pub struct SpeechRecognizer {
     whisper_context: Option<Arc<WhisperContext>>,
     is_initialized: bool,
+    loaded_model: Option<ModelInfo>,
     sample_rate: i32,
+    use_gpu: bool,
+    n_threads: i32,
+    partial_sampling: SamplingMode,
+    final_sampling: SamplingMode,
+    initial_prompt: String,
+    translate: bool,
+    partial_flags: ContextFlags,
+    final_flags: ContextFlags,
+}
+
+/// Default decoding bias: common frontend vocabulary that Whisper otherwise
+/// mangles ("use effect", "web socket", ...). User-configurable via
+/// `set_initial_prompt`.
+const DEFAULT_INITIAL_PROMPT: &str =
+    "React, TypeScript, JavaScript, useEffect, useState, WebSocket, API, CSS, frontend, Node.js, Next.js";
+
+/// Default Whisper thread count: one per core, capped at 8 where the
+/// returns diminish and the fans spin up.
+fn default_n_threads() -> i32 {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+        .min(8) as i32
 }
 
 impl SpeechRecognizer {
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
         info!("Initializing Speech Recognition system...");
-        
+
         Ok(Self {
             whisper_context: None,
             is_initialized: false,
+            loaded_model: None,
             sample_rate: 16000, // Whisper expects 16kHz
+            use_gpu: true, // Metal on Apple Silicon, falls back to CPU if init fails
+            n_threads: default_n_threads(),
+            partial_sampling: SamplingMode::Greedy,
+            final_sampling: SamplingMode::BeamSearch { beam_size: 5 },
+            initial_prompt: DEFAULT_INITIAL_PROMPT.to_string(),
+            translate: false,
+            partial_flags: ContextFlags::isolated(),
+            final_flags: ContextFlags::contextual(),
         })
     }
 
-    pub fn initialize(&mut self, model_path: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    /// Translate non-English speech to English captions. Enabling this also
+    /// switches the source language to auto-detect.
+    pub fn set_translate(&mut self, enabled: bool) {
+        self.translate = enabled;
+    }
+
+    /// Set the initial prompt used to bias decoding toward domain vocabulary.
+    /// An empty string disables the prompt entirely.
+    pub fn set_initial_prompt(&mut self, text: String) {
+        self.initial_prompt = text;
+    }
+
+    pub fn initial_prompt(&self) -> &str {
+        &self.initial_prompt
+    }
+
+    /// Configure which sampling mode to use for streaming partials and for
+    /// the final chunk of an utterance respectively.
+    pub fn set_sampling_modes(&mut self, partial: SamplingMode, final_: SamplingMode) {
+        self.partial_sampling = partial;
+        self.final_sampling = final_;
+    }
+
+    /// Configure the decoding context flags for streaming partials and for
+    /// final/file transcriptions respectively.
+    pub fn set_context_flags(&mut self, partial: ContextFlags, final_: ContextFlags) {
+        self.partial_flags = partial;
+        self.final_flags = final_;
+    }
+
+    /// Set the number of threads used for inference. Takes effect on the
+    /// next `transcribe_audio` call; noticeably changes streaming chunk
+    /// latency (roughly linear up to the physical core count).
+    pub fn set_n_threads(&mut self, n: usize) {
+        self.n_threads = n.max(1) as i32;
+    }
+
+    pub fn n_threads(&self) -> i32 {
+        self.n_threads
+    }
+
+    /// Select GPU (Metal) or CPU inference. Takes effect on the next
+    /// `initialize` call - the loaded context keeps its backend.
+    pub fn set_use_gpu(&mut self, enabled: bool) {
+        self.use_gpu = enabled;
+    }
+
+    /// Load the Whisper model. The command layer passes the path resolved
+    /// from the Tauri resource dir in a bundled app; when `None` (dev mode,
+    /// unbundled) the repo-relative locations are tried instead.
+    pub fn initialize(&mut self, model_path: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
         if self.is_initialized {
             return Ok(());
         }
 
         info!("Loading Whisper model...");
-        
-        // Use default model or provided path
-        let default_model = "models/ggml-base.en.bin";
-        let model_path = model_path.unwrap_or(default_model);
-        
-        // Try multiple possible locations for the model
-        let possible_paths = vec![
-            "ggml-base.en.bin".to_string(), // First try local to binary
-            model_path.to_string(),
-            format!("../{}", model_path),
-            format!("../../{}", model_path),
-            format!("/Users/thiago/Projects/devcaption/{}", model_path),
-            format!("{}/models/ggml-base.en.bin", std::env::current_dir()?.parent().unwrap_or(std::env::current_dir()?.as_ref()).display()),
-        ];
-        
+
+        let candidates: Vec<PathBuf> = match model_path {
+            Some(path) => vec![path],
+            None => vec![
+                PathBuf::from("ggml-base.en.bin"), // local to the binary
+                PathBuf::from("models/ggml-base.en.bin"),
+                PathBuf::from("../models/ggml-base.en.bin"),
+                PathBuf::from("../../models/ggml-base.en.bin"),
+            ],
+        };
+
         let mut found_path = None;
-        for path in &possible_paths {
-            info!("Checking model path: {}", path);
-            if std::path::Path::new(path).exists() {
+        for path in &candidates {
+            info!("Checking model path: {}", path.display());
+            if path.exists() {
                 found_path = Some(path.clone());
                 break;
             }
         }
-        
-        let final_model_path = found_path.ok_or_else(|| {
-            let cwd = std::env::current_dir().unwrap_or_default();
-            let error_msg = format!(
-                "Whisper model not found. Tried paths: {:?}. Current working directory: {}",
-                possible_paths, cwd.display()
-            );
-            warn!("{}", error_msg);
-            error_msg
-        })?;
-
-        let ctx_params = WhisperContextParameters::default();
-        let ctx = WhisperContext::new_with_params(&final_model_path, ctx_params)
-            .map_err(|e| format!("Failed to load Whisper model from {}: {}", final_model_path, e))?;
 
-        self.whisper_context = Some(Arc::new(ctx));
-        self.is_initialized = true;
-        
+        let final_model_path = found_path
+            .map(|p| p.to_string_lossy().into_owned())
+            .ok_or_else(|| {
+                let cwd = std::env::current_dir().unwrap_or_default();
+                let error_msg = format!(
+                    "Whisper model not found. Tried paths: {:?}. Current working directory: {}",
+                    candidates, cwd.display()
+                );
+                warn!("{}", error_msg);
+                error_msg
+            })?;
+
+        let (ctx, info) = Self::load_context(&final_model_path, self.use_gpu)?;
+        self.install_model(ctx, info);
+
         info!("Whisper model loaded successfully from: {}", final_model_path);
         Ok(())
     }
 
+    /// Load a Whisper context from an explicit path, honoring the GPU
+    /// preference with the same CPU fallback as `initialize`. Pure load:
+    /// nothing on an existing recognizer changes until `install_model`,
+    /// which is what makes mid-session model swaps safe.
+    pub fn load_context(model_path: &str, use_gpu: bool) -> Result<(WhisperContext, ModelInfo), Box<dyn std::error::Error>> {
+        let mut ctx_params = WhisperContextParameters::default();
+        ctx_params.use_gpu(use_gpu);
+
+        let mut gpu_active = use_gpu;
+        let ctx = match WhisperContext::new_with_params(model_path, ctx_params) {
+            Ok(ctx) => {
+                info!("Whisper backend: {}", if use_gpu { "GPU (Metal)" } else { "CPU" });
+                ctx
+            }
+            Err(e) if use_gpu => {
+                // Fall back to CPU gracefully instead of failing outright
+                warn!("GPU init failed ({}), falling back to CPU", e);
+                let mut cpu_params = WhisperContextParameters::default();
+                cpu_params.use_gpu(false);
+                let ctx = WhisperContext::new_with_params(model_path, cpu_params)
+                    .map_err(|e| format!("Failed to load Whisper model from {}: {}", model_path, e))?;
+                info!("Whisper backend: CPU (GPU fallback)");
+                gpu_active = false;
+                ctx
+            }
+            Err(e) => {
+                return Err(format!("Failed to load Whisper model from {}: {}", model_path, e).into());
+            }
+        };
+
+        let variant = variant_from_path(model_path);
+        let info = ModelInfo {
+            multilingual: !variant.ends_with(".en"),
+            variant,
+            path: model_path.to_string(),
+            gpu: gpu_active,
+        };
+
+        Ok((ctx, info))
+    }
+
+    /// Swap in an already-loaded context. Transcriptions hold the recognizer
+    /// lock for their whole run, so a caller holding it here can never
+    /// interrupt one mid-inference, and nothing observes a half-swapped
+    /// recognizer.
+    pub fn install_model(&mut self, ctx: WhisperContext, info: ModelInfo) {
+        self.whisper_context = Some(Arc::new(ctx));
+        self.loaded_model = Some(info);
+        self.is_initialized = true;
+    }
+
     pub fn transcribe_audio(&self, audio_data: &[f32]) -> Result<TranscriptionResult, Box<dyn std::error::Error>> {
+        self.transcribe_audio_with_mode(audio_data, true)
+    }
+
+    pub fn transcribe_audio_with_mode(&self, audio_data: &[f32], is_final: bool) -> Result<TranscriptionResult, Box<dyn std::error::Error>> {
         if !self.is_initialized {
             return Err("Speech recognizer not initialized".into());
         }
@@ -80,32 +278,54 @@ impl SpeechRecognizer {
         let ctx = self.whisper_context.as_ref()
             .ok_or("Whisper context not available")?;
 
-        info!("Starting transcription of {} samples", audio_data.len());
+        let sampling = if is_final { self.final_sampling } else { self.partial_sampling };
+
+        info!("Starting transcription of {} samples ({:?})", audio_data.len(), sampling);
 
         // Audio should already be mono and at 16kHz from the capture system
         let processed_audio = audio_data.to_vec();
 
         // Set up parameters for transcription
-        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
-        params.set_n_threads(4);
-        params.set_translate(false);
-        params.set_language(Some("en"));
+        let mut params = FullParams::new(sampling.to_strategy());
+        params.set_n_threads(self.n_threads);
+        params.set_translate(self.translate);
+        if self.translate {
+            // Auto-detect the source language when translating
+            params.set_language(None);
+        } else {
+            params.set_language(Some("en"));
+        }
         params.set_print_special(false);
         params.set_print_progress(false);
         params.set_print_realtime(false);
         params.set_print_timestamps(false);
-        params.set_no_context(true);
-        params.set_single_segment(true);
+        let flags = if is_final { self.final_flags } else { self.partial_flags };
+        params.set_no_context(flags.no_context);
+        params.set_single_segment(flags.single_segment);
+        if !self.initial_prompt.is_empty() {
+            params.set_initial_prompt(&self.initial_prompt);
+        }
 
         // Run inference
         let mut state = ctx.create_state()?;
         state.full(params, &processed_audio)?;
 
+        // Only meaningful when the language was auto-detected; with the
+        // language pinned to English the id would always just echo "en"
+        let detected_language = if self.translate {
+            state.full_lang_id().ok()
+                .and_then(whisper_rs::get_lang_str)
+                .map(|lang| lang.to_string())
+        } else {
+            None
+        };
+
         // Get the transcribed text
         let num_segments = state.full_n_segments()?;
         let mut text = String::new();
         let mut total_confidence = 0.0;
         let mut token_count = 0;
+        let mut words = Vec::new();
 
         for segment_index in 0..num_segments {
             let segment_text = state.full_get_segment_text(segment_index)?;
@@ -116,6 +336,13 @@ impl SpeechRecognizer {
                 let token_prob = state.full_get_token_prob(segment_index, token_index)?;
                 total_confidence += token_prob;
                 token_count += 1;
+
+                // Keep per-token confidence for the proofreading UI, minus
+                // whisper's special/control tokens - they aren't words
+                let token_text = state.full_get_token_text(segment_index, token_index)?;
+                if !token_text.starts_with("[_") && !token_text.starts_with("<|") {
+                    words.push(WordConfidence { text: token_text, prob: token_prob });
+                }
             }
         }
 
@@ -130,6 +357,10 @@ impl SpeechRecognizer {
             confidence: confidence as f64,
             timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64,
             is_final: true,
+            segments: num_segments as usize,
+            words,
+            channel: None,
+            detected_language,
         };
 
         info!("Transcription completed: '{}' (confidence: {:.2})", result.text, result.confidence);
@@ -156,6 +387,11 @@ impl SpeechRecognizer {
     pub fn is_ready(&self) -> bool {
         self.is_initialized && self.whisper_context.is_some()
     }
+
+    /// Metadata for the model currently in memory; None before `initialize`.
+    pub fn loaded_model(&self) -> Option<&ModelInfo> {
+        self.loaded_model.as_ref()
+    }
 }
 
 impl Default for SpeechRecognizer {
@@ -165,7 +401,16 @@ impl Default for SpeechRecognizer {
             Self {
                 whisper_context: None,
                 is_initialized: false,
+                loaded_model: None,
                 sample_rate: 16000,
+                use_gpu: true,
+                n_threads: default_n_threads(),
+                partial_sampling: SamplingMode::Greedy,
+                final_sampling: SamplingMode::BeamSearch { beam_size: 5 },
+                initial_prompt: DEFAULT_INITIAL_PROMPT.to_string(),
+                translate: false,
+                partial_flags: ContextFlags::isolated(),
+                final_flags: ContextFlags::contextual(),
             }
         })
     }
@@ -192,4 +437,36 @@ pub fn apply_noise_reduction(audio_data: &mut [f32], noise_level: f32) {
             *sample = 0.0;
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn initial_prompt_is_stored_for_transcription() {
+        let mut recognizer = SpeechRecognizer::new().unwrap();
+        assert_eq!(recognizer.initial_prompt(), DEFAULT_INITIAL_PROMPT);
+
+        recognizer.set_initial_prompt("Rust, Tauri, Whisper".to_string());
+        assert_eq!(recognizer.initial_prompt(), "Rust, Tauri, Whisper");
+
+        recognizer.set_initial_prompt(String::new());
+        assert_eq!(recognizer.initial_prompt(), "");
+    }
+
+    #[test]
+    fn both_sampling_strategies_run_on_short_buffer() {
+        let mut recognizer = SpeechRecognizer::new().unwrap();
+        if recognizer.initialize(None).is_err() {
+            // No model in this checkout - nothing to exercise
+            return;
+        }
+
+        recognizer.set_sampling_modes(SamplingMode::Greedy, SamplingMode::BeamSearch { beam_size: 2 });
+
+        let silence = vec![0.0f32; 16000]; // 1 second at 16kHz
+        recognizer.transcribe_audio_with_mode(&silence, false).expect("greedy run failed");
+        recognizer.transcribe_audio_with_mode(&silence, true).expect("beam search run failed");
+    }
 }
\ No newline at end of file
@@ -1,45 +1,341 @@
 use cpal::traits::{HostTrait, DeviceTrait};
 use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+// Programmatic Aggregate Device creation via Core Audio, so macOS users
+// don't have to follow the manual Audio MIDI Setup steps from
+// get_setup_instructions by hand
+#[cfg(target_os = "macos")]
+mod aggregate_device {
+    use std::ffi::{CStr, CString};
+    use std::os::raw::c_void;
+    use std::ptr;
+    use coreaudio_sys::*;
+    use log::info;
+
+    const AGGREGATE_NAME: &str = "DevCaption Aggregate";
+    // Stable UID so we can find (and reuse) our own aggregate on later runs
+    const AGGREGATE_UID: &str = "com.devcaption.aggregate";
+
+    fn cfstring(s: &str) -> CFStringRef {
+        let c = CString::new(s).expect("no interior NUL in CFString source");
+        unsafe { CFStringCreateWithCString(kCFAllocatorDefault, c.as_ptr(), kCFStringEncodingUTF8) }
+    }
+
+    fn cfstring_to_string(s: CFStringRef) -> String {
+        let mut buf = [0i8; 256];
+        unsafe {
+            if CFStringGetCString(s, buf.as_mut_ptr(), buf.len() as CFIndex, kCFStringEncodingUTF8) != 0 {
+                CStr::from_ptr(buf.as_ptr()).to_string_lossy().into_owned()
+            } else {
+                String::new()
+            }
+        }
+    }
+
+    fn global_address(selector: AudioObjectPropertySelector) -> AudioObjectPropertyAddress {
+        AudioObjectPropertyAddress {
+            mSelector: selector,
+            mScope: kAudioObjectPropertyScopeGlobal,
+            mElement: kAudioObjectPropertyElementMaster,
+        }
+    }
+
+    fn string_property(device: AudioObjectID, selector: AudioObjectPropertySelector) -> Option<String> {
+        let addr = global_address(selector);
+        let mut value: CFStringRef = ptr::null();
+        let mut size = std::mem::size_of::<CFStringRef>() as u32;
+
+        let status = unsafe {
+            AudioObjectGetPropertyData(device, &addr, 0, ptr::null(), &mut size, &mut value as *mut _ as *mut c_void)
+        };
+        if status != 0 || value.is_null() {
+            return None;
+        }
+
+        let result = cfstring_to_string(value);
+        unsafe { CFRelease(value as CFTypeRef) };
+        Some(result)
+    }
+
+    fn all_device_ids() -> Result<Vec<AudioObjectID>, Box<dyn std::error::Error>> {
+        let addr = global_address(kAudioHardwarePropertyDevices);
+        let mut size: u32 = 0;
+
+        let status = unsafe {
+            AudioObjectGetPropertyDataSize(kAudioObjectSystemObject, &addr, 0, ptr::null(), &mut size)
+        };
+        if status != 0 {
+            return Err(format!("Could not query device list size (OSStatus {})", status).into());
+        }
+
+        let count = size as usize / std::mem::size_of::<AudioObjectID>();
+        let mut ids = vec![AudioObjectID::default(); count];
+
+        let status = unsafe {
+            AudioObjectGetPropertyData(kAudioObjectSystemObject, &addr, 0, ptr::null(), &mut size, ids.as_mut_ptr() as *mut c_void)
+        };
+        if status != 0 {
+            return Err(format!("Could not query device list (OSStatus {})", status).into());
+        }
+
+        Ok(ids)
+    }
+
+    fn default_input_uid() -> Result<String, Box<dyn std::error::Error>> {
+        let addr = global_address(kAudioHardwarePropertyDefaultInputDevice);
+        let mut device: AudioObjectID = 0;
+        let mut size = std::mem::size_of::<AudioObjectID>() as u32;
+
+        let status = unsafe {
+            AudioObjectGetPropertyData(kAudioObjectSystemObject, &addr, 0, ptr::null(), &mut size, &mut device as *mut _ as *mut c_void)
+        };
+        if status != 0 || device == 0 {
+            return Err("No default input device available".into());
+        }
+
+        string_property(device, kAudioDevicePropertyDeviceUID)
+            .ok_or_else(|| "Could not read default input device UID".into())
+    }
+
+    fn find_blackhole_uid() -> Result<Option<String>, Box<dyn std::error::Error>> {
+        for id in all_device_ids()? {
+            if let Some(name) = string_property(id, kAudioObjectPropertyName) {
+                if name.to_lowercase().contains("blackhole") {
+                    return Ok(string_property(id, kAudioDevicePropertyDeviceUID));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Look for an aggregate we created on a previous run (matched by UID).
+    fn find_existing() -> Result<Option<String>, Box<dyn std::error::Error>> {
+        for id in all_device_ids()? {
+            if string_property(id, kAudioDevicePropertyDeviceUID).as_deref() == Some(AGGREGATE_UID) {
+                let name = string_property(id, kAudioObjectPropertyName)
+                    .unwrap_or_else(|| AGGREGATE_NAME.to_string());
+                return Ok(Some(name));
+            }
+        }
+        Ok(None)
+    }
+
+    /// A `{ "uid": <device uid> }` sub-device entry for the aggregate's
+    /// sub-device list (kAudioSubDeviceUIDKey).
+    unsafe fn sub_device_dict(uid: &str) -> CFDictionaryRef {
+        let key = cfstring("uid");
+        let value = cfstring(uid);
+        let keys = [key as *const c_void];
+        let values = [value as *const c_void];
+
+        let dict = CFDictionaryCreate(
+            kCFAllocatorDefault,
+            keys.as_ptr() as *mut *const c_void,
+            values.as_ptr() as *mut *const c_void,
+            1,
+            &kCFTypeDictionaryKeyCallBacks,
+            &kCFTypeDictionaryValueCallBacks,
+        );
+
+        CFRelease(key as CFTypeRef);
+        CFRelease(value as CFTypeRef);
+        dict
+    }
+
+    pub fn create_or_reuse() -> Result<String, Box<dyn std::error::Error>> {
+        if let Some(name) = find_existing()? {
+            info!("Reusing existing aggregate device: {}", name);
+            return Ok(name);
+        }
+
+        let blackhole_uid = find_blackhole_uid()?
+            .ok_or("BlackHole not found - install it first (brew install blackhole-2ch)")?;
+        let mic_uid = default_input_uid()?;
+
+        info!("Creating aggregate device from BlackHole ({}) + default input ({})", blackhole_uid, mic_uid);
+
+        unsafe {
+            let sub_devices = [
+                sub_device_dict(&blackhole_uid) as *const c_void,
+                sub_device_dict(&mic_uid) as *const c_void,
+            ];
+            let sub_list = CFArrayCreate(
+                kCFAllocatorDefault,
+                sub_devices.as_ptr() as *mut *const c_void,
+                sub_devices.len() as CFIndex,
+                &kCFTypeArrayCallBacks,
+            );
+
+            // Keys per CoreAudio/AudioHardware.h: kAudioAggregateDeviceNameKey,
+            // kAudioAggregateDeviceUIDKey, kAudioAggregateDeviceSubDeviceListKey
+            let name_key = cfstring("name");
+            let name_value = cfstring(AGGREGATE_NAME);
+            let uid_key = cfstring("uid");
+            let uid_value = cfstring(AGGREGATE_UID);
+            let sub_list_key = cfstring("subdevices");
+
+            let keys = [
+                name_key as *const c_void,
+                uid_key as *const c_void,
+                sub_list_key as *const c_void,
+            ];
+            let values = [
+                name_value as *const c_void,
+                uid_value as *const c_void,
+                sub_list as *const c_void,
+            ];
+            let description = CFDictionaryCreate(
+                kCFAllocatorDefault,
+                keys.as_ptr() as *mut *const c_void,
+                values.as_ptr() as *mut *const c_void,
+                keys.len() as CFIndex,
+                &kCFTypeDictionaryKeyCallBacks,
+                &kCFTypeDictionaryValueCallBacks,
+            );
+
+            let mut aggregate_id: AudioObjectID = 0;
+            let status = AudioHardwareCreateAggregateDevice(description, &mut aggregate_id);
+
+            for value in keys.iter().chain(values.iter()) {
+                CFRelease(*value as CFTypeRef);
+            }
+            for sub in &sub_devices {
+                CFRelease(*sub as CFTypeRef);
+            }
+            CFRelease(description as CFTypeRef);
+
+            if status != 0 {
+                return Err(format!("AudioHardwareCreateAggregateDevice failed (OSStatus {})", status).into());
+            }
+
+            info!("Aggregate device created (id {})", aggregate_id);
+        }
+
+        Ok(AGGREGATE_NAME.to_string())
+    }
+}
+
+/// One system-audio capture candidate, ranked so the UI can present a menu
+/// while `find_system_audio_device` keeps auto-picking the best.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemAudioDevice {
+    pub name: String,
+    /// Routing flavor: "monitor" (PulseAudio/PipeWire), "blackhole",
+    /// "aggregate", "multi_output" or "loopback" (WASAPI).
+    pub device_type: String,
+    /// Lower is preferred; the auto-pick takes the lowest.
+    pub priority: u8,
+    /// Input channel count, when the backend reports one.
+    pub channels: Option<u16>,
+}
 
 pub struct SystemAudioHelper;
 
 impl SystemAudioHelper {
-    pub fn find_system_audio_device() -> Result<Option<String>, Box<dyn std::error::Error>> {
-        info!("Searching for system audio devices (BlackHole, Aggregate, etc.)...");
-        
-        let host = cpal::default_host();
+    /// Every plausible system-audio capture device, best first, for users
+    /// who have several (BlackHole plus an aggregate, multiple monitors).
+    pub fn list_system_audio_devices() -> Result<Vec<SystemAudioDevice>, Box<dyn std::error::Error>> {
+        // On Windows, WASAPI loopback captures the default render endpoint
+        // directly - there is only ever the one candidate
+        #[cfg(target_os = "windows")]
+        {
+            if let Some(device) = crate::audio_capture::current_host().default_output_device() {
+                let channels = device.default_output_config().ok().map(|c| c.channels());
+                let name = device.name()?;
+                return Ok(vec![SystemAudioDevice {
+                    name: format!("{} (WASAPI Loopback)", name),
+                    device_type: "loopback".to_string(),
+                    priority: 0,
+                    channels,
+                }]);
+            }
+            info!("No default output device available for WASAPI loopback");
+            return Ok(Vec::new());
+        }
+
+        #[allow(unreachable_code)]
+        let host = crate::audio_capture::current_host();
         let devices = host.input_devices()?;
-        
-        // Priority order: BlackHole > Aggregate > Multi-Output
+
+        // Priority order: default sink monitor > other monitors (Linux),
+        // then BlackHole > Aggregate > Multi-Output (macOS)
         let mut found_devices = Vec::new();
-        
+
         for device in devices {
             if let Ok(name) = device.name() {
                 let name_lower = name.to_lowercase();
-                if name_lower.contains("blackhole") {
-                    found_devices.push((1, name.clone())); // Highest priority
+
+                // PulseAudio/PipeWire expose each sink's output as a
+                // "<sink>.monitor" source - that's system audio on Linux,
+                // no manual routing required
+                #[cfg(target_os = "linux")]
+                if name_lower.ends_with(".monitor") {
+                    let priority = if name_lower.contains("default") { 0 } else { 1 };
+                    found_devices.push(SystemAudioDevice {
+                        name: name.clone(),
+                        device_type: "monitor".to_string(),
+                        priority,
+                        channels: device.default_input_config().ok().map(|c| c.channels()),
+                    });
+                    continue;
+                }
+
+                let classified = if name_lower.contains("blackhole") {
+                    Some(("blackhole", 2)) // Highest macOS priority
                 } else if name_lower.contains("aggregate") {
-                    found_devices.push((2, name.clone()));
+                    Some(("aggregate", 3))
                 } else if name_lower.contains("multi") {
-                    found_devices.push((3, name.clone()));
+                    Some(("multi_output", 4))
+                } else {
+                    None
+                };
+
+                if let Some((device_type, priority)) = classified {
+                    found_devices.push(SystemAudioDevice {
+                        name: name.clone(),
+                        device_type: device_type.to_string(),
+                        priority,
+                        channels: device.default_input_config().ok().map(|c| c.channels()),
+                    });
                 }
             }
         }
-        
-        // Sort by priority and return the best option
-        found_devices.sort_by_key(|&(priority, _)| priority);
-        
-        if let Some((_, device_name)) = found_devices.first() {
-            info!("Found system audio device: {}", device_name);
-            Ok(Some(device_name.clone()))
+
+        found_devices.sort_by_key(|device| device.priority);
+        Ok(found_devices)
+    }
+
+    pub fn find_system_audio_device() -> Result<Option<String>, Box<dyn std::error::Error>> {
+        info!("Searching for system audio devices (BlackHole, Aggregate, etc.)...");
+
+        let found_devices = Self::list_system_audio_devices()?;
+        if let Some(device) = found_devices.first() {
+            info!("Found system audio device: {}", device.name);
+            Ok(Some(device.name.clone()))
         } else {
             info!("No system audio devices found. Install BlackHole for system audio capture.");
             Ok(None)
         }
     }
 
+    /// Create (or reuse) an Aggregate Device combining BlackHole with the
+    /// default input, replacing the manual Audio MIDI Setup steps. Returns
+    /// the device name, ready to pass to `start_audio_capture`.
+    pub fn create_system_audio_aggregate() -> Result<String, Box<dyn std::error::Error>> {
+        #[cfg(target_os = "macos")]
+        {
+            aggregate_device::create_or_reuse()
+        }
+
+        #[cfg(not(target_os = "macos"))]
+        {
+            Err("Aggregate device creation is only supported on macOS".into())
+        }
+    }
+
     pub fn get_device_info() -> Result<String, Box<dyn std::error::Error>> {
-        let host = cpal::default_host();
+        let host = crate::audio_capture::current_host();
         let devices = host.input_devices()?;
         
         let mut info = String::new();
@@ -47,9 +343,7 @@ impl SystemAudioHelper {
         
         for (i, device) in devices.enumerate() {
             if let Ok(name) = device.name() {
-                let device_type = if name.to_lowercase().contains("blackhole") ||
-                                    name.to_lowercase().contains("aggregate") ||
-                                    name.to_lowercase().contains("multi") {
+                let device_type = if Self::is_system_audio_device(&name) {
                     " [SYSTEM AUDIO]"
                 } else {
                     " [MICROPHONE]"
@@ -79,7 +373,9 @@ impl SystemAudioHelper {
         name_lower.contains("blackhole") ||
         name_lower.contains("aggregate") ||
         name_lower.contains("multi") ||
-        name_lower.contains("system audio")
+        name_lower.contains("system audio") ||
+        name_lower.contains("loopback") ||
+        name_lower.ends_with(".monitor")
     }
 
     pub fn get_setup_instructions() -> String {
@@ -11,10 +11,22 @@ interface TranscriptionResult {
 }
 
 interface AudioLevel {
-  level: number;
+  rms: number;
+  peak: number;
+  raw_rms: number;
+  raw_peak: number;
   timestamp: number;
 }
 
+interface InterviewResponse {
+  text: string;
+  usage: {
+    prompt_tokens: number;
+    candidates_tokens: number;
+    total_tokens: number;
+  } | null;
+}
+
 interface ResponseHistory {
   question: string;
   response: string;
@@ -34,6 +46,7 @@ function App() {
   const [responseHistory, setResponseHistory] = useState<ResponseHistory[]>([]);
   const [isLoadingResponse, setIsLoadingResponse] = useState(false);
   const [audioLevel, setAudioLevel] = useState(0);
+  const [audioPeak, setAudioPeak] = useState(0);
   const [audioDevices, setAudioDevices] = useState<string[]>([]);
   const [selectedDevice, setSelectedDevice] = useState<string>("");
   const [hasPermissions, setHasPermissions] = useState(false);
@@ -60,12 +73,13 @@ function App() {
     addDebugLog("Setting up event listeners");
 
     const unlisten = listen<AudioLevel>("audio-level", (event) => {
-      const { level } = event.payload;
-      setAudioLevel(level);
+      const { rms, peak } = event.payload;
+      setAudioLevel(rms);
+      setAudioPeak(peak);
 
       // Update audio level visualization
       if (audioLevelRef.current) {
-        const percentage = Math.min(level * 100, 100);
+        const percentage = Math.min(rms * 100, 100);
         audioLevelRef.current.style.width = `${percentage}%`;
       }
     });
@@ -170,6 +184,7 @@ function App() {
       await invoke("stop_audio_capture");
       setIsRecording(false);
       setAudioLevel(0);
+      setAudioPeak(0);
       if (audioLevelRef.current) {
         audioLevelRef.current.style.width = "0%";
       }
@@ -189,7 +204,7 @@ function App() {
       }
 
       setIsLoadingResponse(true);
-      const response = await invoke<string>("get_interview_response", {
+      const response = await invoke<InterviewResponse>("get_interview_response", {
         transcription: newText,
         isFirstQuestion: isFirstQuestion,
       });
@@ -199,7 +214,7 @@ function App() {
         ...prev,
         {
           question: newText,
-          response: response,
+          response: response.text,
           timestamp: Date.now(),
         },
       ]);
@@ -290,7 +305,7 @@ function App() {
             <div ref={audioLevelRef} className="audio-level-fill"></div>
           </div>
           <span className="audio-level-text">
-            {(audioLevel * 100).toFixed(1)}%
+            {(audioLevel * 100).toFixed(1)}% (peak {(audioPeak * 100).toFixed(1)}%)
           </span>
         </div>
 